@@ -0,0 +1,113 @@
+//! Command-line arguments, so file associations and scripts can start laminated in a session.
+use crate::session::SessionType;
+use eyre::eyre;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: laminated [LOG] [OPTIONS]
+
+Arguments:
+  [LOG]                 A log file to open
+
+Options:
+  --puzzle <SPEC>       The puzzle to start on, like cube:5, fto:4, megaminx,
+                        pentultimate or little-chop
+  --seed <SEED>         Scramble the puzzle with this seed, a whole number
+  --prefs <PATH>        The preferences file to use instead of ./preferences.json
+  -h, --help            Print this message";
+
+#[derive(Debug, Default)]
+pub struct Args {
+    pub log: Option<PathBuf>,
+    pub puzzle: Option<SessionType>,
+    /// The same seed scrambles the same way in the same version of laminated.
+    pub seed: Option<u64>,
+    pub prefs: Option<PathBuf>,
+    pub help: bool,
+}
+
+impl Args {
+    /// Whether the arguments say what session to start with,
+    /// instead of offering to restore the last one.
+    pub fn picks_session(&self) -> bool {
+        self.log.is_some() || self.puzzle.is_some() || self.seed.is_some()
+    }
+}
+
+/// Parses the arguments, without the program name.
+pub fn parse(args: impl IntoIterator<Item = String>) -> eyre::Result<Args> {
+    let mut parsed = Args::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| eyre!("{arg} needs a value"));
+        match arg.as_str() {
+            "-h" | "--help" => parsed.help = true,
+            "--puzzle" => parsed.puzzle = Some(value()?.parse()?),
+            "--seed" => {
+                let seed = value()?;
+                parsed.seed = Some(
+                    seed.parse()
+                        .map_err(|_| eyre!("Invalid seed {seed}, it should be a whole number"))?,
+                );
+            }
+            "--prefs" => parsed.prefs = Some(PathBuf::from(value()?)),
+            _ if arg.starts_with('-') => return Err(eyre!("Unknown option {arg}")),
+            _ if parsed.log.is_some() => return Err(eyre!("Only one log file can be opened")),
+            _ => parsed.log = Some(PathBuf::from(arg)),
+        }
+    }
+    if parsed.log.is_some() && (parsed.puzzle.is_some() || parsed.seed.is_some()) {
+        return Err(eyre!("A log file already has a puzzle and scramble"));
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{CubePuzzle, OctaPuzzle};
+
+    fn parse_str(args: &str) -> eyre::Result<Args> {
+        parse(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn arguments() {
+        let args = parse_str("--puzzle fto:4 --seed 12 --prefs my-prefs.json").unwrap();
+        assert!(matches!(
+            args.puzzle,
+            Some(SessionType::Octa(OctaPuzzle::Fto(4)))
+        ));
+        assert_eq!(args.seed, Some(12));
+        assert_eq!(args.prefs, Some(PathBuf::from("my-prefs.json")));
+        assert!(args.picks_session());
+
+        let args = parse_str("solve.log").unwrap();
+        assert_eq!(args.log, Some(PathBuf::from("solve.log")));
+
+        let args = parse_str("--prefs p.json").unwrap();
+        assert!(!args.picks_session());
+        assert!(matches!(
+            parse_str("--puzzle cube:5").unwrap().puzzle,
+            Some(SessionType::Cube(CubePuzzle::Nnn(5)))
+        ));
+    }
+
+    #[test]
+    fn bad_arguments() {
+        for args in [
+            "--puzzle",
+            "--puzzle cube",
+            "--puzzle cube:0",
+            "--puzzle cube:1",
+            "--puzzle fto:1",
+            "--puzzle fto:64",
+            "--seed -3",
+            "--verbose",
+            "a.log b.log",
+            "a.log --seed 3",
+        ] {
+            assert!(parse_str(args).is_err(), "{args}");
+        }
+    }
+}
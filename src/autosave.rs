@@ -0,0 +1,170 @@
+//! Keeps sessions that were not saved when laminated closed,
+//! and remembers the log files used recently.
+use crate::log_format::{self, LogFormat};
+use crate::session::{SessionEnum, SessionLog};
+use serde::Deserialize;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// How often the session is checked for changes to autosave, in milliseconds.
+const AUTOSAVE_INTERVAL: f64 = 10_000.0;
+const AUTOSAVE_FILE: &str = "autosave.json";
+const RECENT_FILES_FILE: &str = "recent.json";
+/// How many recent files the File menu lists.
+const RECENT_FILES_LEN: usize = 10;
+
+/// The directory laminated keeps its own files in, like the autosave.
+pub fn data_dir() -> PathBuf {
+    let env_dir = |var: &str| {
+        std::env::var_os(var)
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+    };
+    let base = if cfg!(windows) {
+        env_dir("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        env_dir("HOME").map(|home| home.join("Library/Application Support"))
+    } else {
+        env_dir("XDG_DATA_HOME").or_else(|| env_dir("HOME").map(|home| home.join(".local/share")))
+    };
+    base.unwrap_or_else(|| PathBuf::from(".")).join("laminated")
+}
+
+/// A session that had changes that were not saved when laminated last closed.
+#[derive(Serialize, Deserialize)]
+pub struct AutosaveFile {
+    /// Where the session was last saved or loaded from.
+    pub save_path: Option<PathBuf>,
+    /// The format of the file at `save_path`.
+    #[serde(default)]
+    pub save_format: LogFormat,
+    /// The log, in the compact format.
+    log: String,
+}
+
+impl AutosaveFile {
+    pub fn log(&self) -> eyre::Result<SessionLog> {
+        log_format::decode(self.log.as_bytes())
+    }
+
+    /// The autosave left by the last run, if there is one.
+    pub fn load() -> eyre::Result<Option<Self>> {
+        let path = data_dir().join(AUTOSAVE_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_slice(&std::fs::read(path)?)?))
+    }
+
+    pub fn discard() -> eyre::Result<()> {
+        let path = data_dir().join(AUTOSAVE_FILE);
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Autosaves the session while it has changes that are not in a log file.
+#[derive(Default)]
+pub struct Autosave {
+    /// When the session was last checked for changes, from `FrameInput::accumulated_time`.
+    last_check: f64,
+    /// The compact log of the session when it was saved, loaded or made.
+    /// None if it has never been in a log file, like a restored session.
+    saved: Option<Vec<u8>>,
+    /// The compact log last autosaved, if the autosave file is there.
+    autosaved: Option<Vec<u8>>,
+}
+
+fn compact_log(session: &SessionEnum) -> eyre::Result<Vec<u8>> {
+    log_format::encode(&session.to_log(false), LogFormat::Compact)
+}
+
+impl Autosave {
+    /// Counts the session as it is now as saved, so only later changes are autosaved.
+    pub fn mark_saved(&mut self, session: &SessionEnum) -> eyre::Result<()> {
+        self.saved = Some(compact_log(session)?);
+        Ok(())
+    }
+
+    /// Counts the session as never saved, so it is autosaved on the next check.
+    pub fn mark_unsaved(&mut self) {
+        self.saved = None;
+    }
+
+    /// Autosaves the session if it changed, at most once every `AUTOSAVE_INTERVAL`.
+    pub fn update(&mut self, time: f64, session: &SessionEnum) -> eyre::Result<()> {
+        if time - self.last_check < AUTOSAVE_INTERVAL {
+            return Ok(());
+        }
+        self.last_check = time;
+        self.save(session)
+    }
+
+    /// Autosaves the session now if it changed, like when laminated closes.
+    /// Removes the autosave once the changes are saved.
+    pub fn save(&mut self, session: &SessionEnum) -> eyre::Result<()> {
+        let log = compact_log(session)?;
+        if self.saved.as_ref() == Some(&log) {
+            if self.autosaved.take().is_some() {
+                AutosaveFile::discard()?;
+            }
+        } else if self.autosaved.as_ref() != Some(&log) {
+            let file = AutosaveFile {
+                save_path: session.save_path().clone(),
+                save_format: session.save_format().unwrap_or_default(),
+                log: String::from_utf8(log.clone())?,
+            };
+            std::fs::create_dir_all(data_dir())?;
+            std::fs::write(data_dir().join(AUTOSAVE_FILE), serde_json::to_vec(&file)?)?;
+            self.autosaved = Some(log);
+        }
+        Ok(())
+    }
+}
+
+/// The log files saved or loaded most recently, most recent first.
+pub fn recent_files() -> Vec<PathBuf> {
+    std::fs::read(data_dir().join(RECENT_FILES_FILE))
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Moves the path to the front of the recent files and returns them.
+pub fn add_recent_file(path: &Path) -> eyre::Result<Vec<PathBuf>> {
+    let mut files = recent_files();
+    push_recent(&mut files, path.to_path_buf());
+    std::fs::create_dir_all(data_dir())?;
+    std::fs::write(
+        data_dir().join(RECENT_FILES_FILE),
+        serde_json::to_vec(&files)?,
+    )?;
+    Ok(files)
+}
+
+fn push_recent(files: &mut Vec<PathBuf>, path: PathBuf) {
+    files.retain(|file| file != &path);
+    files.insert(0, path);
+    files.truncate(RECENT_FILES_LEN);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recent_order() {
+        let mut files = vec![];
+        for name in ["a.log", "b.log", "a.log", "c.log"] {
+            push_recent(&mut files, PathBuf::from(name));
+        }
+        assert_eq!(files, ["c.log", "a.log", "b.log"].map(PathBuf::from));
+        for i in 0..20 {
+            push_recent(&mut files, PathBuf::from(format!("{i}.log")));
+        }
+        assert_eq!(files.len(), RECENT_FILES_LEN);
+        assert_eq!(files[0], PathBuf::from("19.log"));
+    }
+}
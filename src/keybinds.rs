@@ -0,0 +1,322 @@
+use crate::puzzle::common::*;
+use crate::util::key_names::{key_from_name, key_name};
+use eyre::eyre;
+use serde::Deserialize;
+use serde::Serialize;
+use three_d::Key;
+
+/// What pressing a bound key does.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum KeyAction {
+    /// Twists the face clockwise (direction 1) or counterclockwise (direction -1).
+    /// The layers are counted from the face starting at 1. If there are none,
+    /// the layers held with layer modifiers are turned, or else the layers
+    /// selected by the number keys.
+    Twist {
+        face: String,
+        direction: i8,
+        #[serde(default)]
+        layers: Vec<usize>,
+    },
+    /// Turns the whole puzzle around the face.
+    Rotate { face: String, direction: i8 },
+    /// While held, twists from keybinds turn this layer, counted from the face starting at 1.
+    Layer(usize),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Keybind {
+    /// Name of the key, from `util::key_names`.
+    pub key: String,
+    #[serde(default)]
+    pub shift: bool,
+    pub action: KeyAction,
+}
+
+impl Keybind {
+    pub fn matches(&self, key: Key, shift: bool) -> bool {
+        key_from_name(&self.key) == Some(key) && self.shift == shift
+    }
+}
+
+fn face_from_name<Ray: RaySystem>(name: &str) -> eyre::Result<Ray> {
+    Ray::from_name(name).ok_or_else(|| eyre!("Unknown face {name} in keybind"))
+}
+
+fn grips_from_layers<Ray: RaySystem>(
+    puzzle: &Puzzle<Ray>,
+    face: Ray,
+    layers: &[usize],
+) -> eyre::Result<Vec<Grip>> {
+    let grips = puzzle.grips_from_face(face);
+    layers
+        .iter()
+        .map(|&layer| {
+            layer
+                .checked_sub(1)
+                .and_then(|i| grips.get(i))
+                .cloned()
+                .ok_or_else(|| eyre!("Layer {layer} does not exist on this puzzle"))
+        })
+        .collect()
+}
+
+impl KeyAction {
+    /// The twist that the action does, or None for layer modifiers.
+    /// `held_layers` are the layers of the layer modifiers that are held, and
+    /// `selected_grips` gives the grips selected by the number keys for a face.
+    pub fn twist<Ray: RaySystem>(
+        &self,
+        puzzle: &Puzzle<Ray>,
+        held_layers: &[usize],
+        selected_grips: impl FnOnce(Ray) -> Vec<Grip>,
+    ) -> eyre::Result<Option<Twist<Ray>>> {
+        match self {
+            Self::Twist {
+                face,
+                direction,
+                layers,
+            } => {
+                let face = face_from_name::<Ray>(face)?;
+                let grips = if !layers.is_empty() {
+                    grips_from_layers(puzzle, face, layers)?
+                } else if !held_layers.is_empty() {
+                    grips_from_layers(puzzle, face, held_layers)?
+                } else {
+                    selected_grips(face)
+                };
+                Ok(Some(((face, face.clockwise() * direction), grips)))
+            }
+            Self::Rotate { face, direction } => {
+                let face = face_from_name::<Ray>(face)?;
+                Ok(Some((
+                    (face, face.clockwise() * direction),
+                    puzzle.grips.clone(),
+                )))
+            }
+            Self::Layer(_) => Ok(None),
+        }
+    }
+}
+
+/// The layers of the layer modifiers among the held keys.
+pub fn held_layers(keybinds: &[Keybind], keys: impl Iterator<Item = Key> + Clone) -> Vec<usize> {
+    keybinds
+        .iter()
+        .filter_map(|bind| match bind.action {
+            KeyAction::Layer(layer)
+                if keys
+                    .clone()
+                    .any(|key| key_from_name(&bind.key) == Some(key)) =>
+            {
+                Some(layer)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Reads a keybind file.
+pub fn load_keybinds(path: &std::path::Path) -> eyre::Result<Vec<Keybind>> {
+    let reader = std::io::BufReader::new(std::fs::File::open(path)?);
+    Ok(serde_json::from_reader(reader)?)
+}
+
+/// Writes a keybind file.
+pub fn save_keybinds(path: &std::path::Path, keybinds: &[Keybind]) -> eyre::Result<()> {
+    std::fs::write(path, serde_json::to_string_pretty(keybinds)?)?;
+    Ok(())
+}
+
+// (key, face, direction) of the twists and rotations in the default keybinds
+
+#[rustfmt::skip]
+const CUBE_TWISTS: &[(Key, &str, i8)] = &[
+    (Key::I, "R", 1), (Key::K, "R", -1),
+    (Key::D, "L", 1), (Key::E, "L", -1),
+    (Key::J, "U", 1), (Key::F, "U", -1),
+    (Key::S, "D", 1), (Key::L, "D", -1),
+    (Key::H, "F", 1), (Key::G, "F", -1),
+    (Key::W, "B", 1), (Key::O, "B", -1),
+];
+#[rustfmt::skip]
+const CUBE_ROTATIONS: &[(Key, &str, i8)] = &[
+    (Key::ArrowLeft, "U", 1), (Key::ArrowRight, "U", -1),
+    (Key::ArrowUp, "R", 1), (Key::ArrowDown, "R", -1),
+    (Key::PageUp, "F", 1), (Key::PageDown, "F", -1),
+];
+
+#[rustfmt::skip]
+const FTO_TWISTS: &[(Key, &str, i8)] = &[
+    (Key::I, "R", 1), (Key::K, "R", -1),
+    (Key::D, "L", 1), (Key::E, "L", -1),
+    (Key::J, "U", 1), (Key::F, "U", -1),
+    (Key::S, "D", 1), (Key::L, "D", -1),
+    (Key::H, "F", 1), (Key::G, "F", -1),
+    (Key::O, "BR", 1), (Key::P, "BR", -1),
+    (Key::W, "BL", 1), (Key::Q, "BL", -1),
+    (Key::U, "BU", 1), (Key::R, "BU", -1),
+];
+#[rustfmt::skip]
+const FTO_ROTATIONS: &[(Key, &str, i8)] = &[
+    (Key::ArrowLeft, "U", 1), (Key::ArrowRight, "U", -1),
+    (Key::ArrowUp, "R", 1), (Key::ArrowDown, "R", -1),
+    (Key::PageUp, "F", 1), (Key::PageDown, "F", -1),
+];
+
+#[rustfmt::skip]
+const DODECA_TWISTS: &[(Key, &str, i8)] = &[
+    (Key::I, "R", 1), (Key::K, "R", -1),
+    (Key::D, "L", 1), (Key::E, "L", -1),
+    (Key::J, "U", 1), (Key::F, "U", -1),
+    (Key::H, "F", 1), (Key::G, "F", -1),
+    (Key::O, "BR", 1), (Key::L, "BR", -1),
+    (Key::W, "BL", 1), (Key::S, "BL", -1),
+    (Key::M, "DR", 1), (Key::U, "DR", -1),
+    (Key::V, "DL", 1), (Key::R, "DL", -1),
+    (Key::Y, "PR", 1), (Key::N, "PR", -1),
+    (Key::T, "PL", 1), (Key::B, "PL", -1),
+    (Key::P, "PB", 1), (Key::Q, "PB", -1),
+    (Key::C, "PD", 1), (Key::X, "PD", -1),
+];
+#[rustfmt::skip]
+const DODECA_ROTATIONS: &[(Key, &str, i8)] = &[
+    (Key::ArrowLeft, "U", 1), (Key::ArrowRight, "U", -1),
+    (Key::ArrowUp, "R", 1), (Key::ArrowDown, "R", -1),
+    (Key::PageUp, "F", 1), (Key::PageDown, "F", -1),
+];
+
+// every turn of the rhombic dodecahedron is a half turn, so one key per face is enough
+#[rustfmt::skip]
+const R_DODECA_TWISTS: &[(Key, &str, i8)] = &[
+    (Key::I, "UR", 1), (Key::E, "UL", 1),
+    (Key::K, "DR", 1), (Key::D, "DL", 1),
+    (Key::J, "FU", 1), (Key::F, "FD", 1),
+    (Key::U, "BU", 1), (Key::R, "BD", 1),
+    (Key::H, "RF", 1), (Key::G, "LF", 1),
+    (Key::O, "RB", 1), (Key::W, "LB", 1),
+];
+#[rustfmt::skip]
+const R_DODECA_ROTATIONS: &[(Key, &str, i8)] = &[
+    (Key::ArrowLeft, "FU", 1), (Key::ArrowRight, "BU", 1),
+    (Key::ArrowUp, "UR", 1), (Key::ArrowDown, "UL", 1),
+];
+
+/// The default keybinds for a puzzle family from `SessionType::family`.
+/// Holding shift with a twist key turns the second layer instead.
+pub fn default_keybinds(family: &str) -> Vec<Keybind> {
+    let (twists, rotations) = match family {
+        "cube" => (CUBE_TWISTS, CUBE_ROTATIONS),
+        "fto" => (FTO_TWISTS, FTO_ROTATIONS),
+        "dodeca" => (DODECA_TWISTS, DODECA_ROTATIONS),
+        "rhombic-dodeca" => (R_DODECA_TWISTS, R_DODECA_ROTATIONS),
+        _ => (&[][..], &[][..]),
+    };
+
+    let twist = |&(key, face, direction): &(Key, &str, i8), shift: bool| Keybind {
+        key: key_name(key).to_string(),
+        shift,
+        action: KeyAction::Twist {
+            face: face.to_string(),
+            direction,
+            layers: if shift { vec![2] } else { vec![] },
+        },
+    };
+    let rotation = |&(key, face, direction): &(Key, &str, i8)| Keybind {
+        key: key_name(key).to_string(),
+        shift: false,
+        action: KeyAction::Rotate {
+            face: face.to_string(),
+            direction,
+        },
+    };
+
+    twists
+        .iter()
+        .map(|bind| twist(bind, false))
+        .chain(twists.iter().map(|bind| twist(bind, true)))
+        .chain(rotations.iter().map(rotation))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzle::cube::{name::*, CubeRay};
+    use crate::puzzle::dodeca::DodecaRay;
+    use crate::puzzle::octa::OctaRay;
+    use crate::puzzle::r_dodeca::RDodecaRay;
+    use itertools::Itertools;
+
+    fn defaults_valid<Ray: RaySystem>(family: &str) {
+        let keybinds = default_keybinds(family);
+        assert!(!keybinds.is_empty());
+        let puzzle: Puzzle<Ray> = Puzzle::make_solved(vec![[1; 2], [-1; 2]]);
+        for bind in &keybinds {
+            assert!(key_from_name(&bind.key).is_some());
+            assert!(bind.action.twist(&puzzle, &[], |_| vec![]).is_ok());
+        }
+        assert!(
+            keybinds
+                .iter()
+                .map(|bind| (&bind.key, bind.shift))
+                .all_unique(),
+            "a key is bound twice in the {family} keybinds"
+        );
+    }
+
+    #[test]
+    fn defaults() {
+        defaults_valid::<CubeRay>("cube");
+        defaults_valid::<OctaRay>("fto");
+        defaults_valid::<DodecaRay>("dodeca");
+        defaults_valid::<RDodecaRay>("rhombic-dodeca");
+    }
+
+    #[test]
+    fn twists() {
+        let puzzle: Puzzle<CubeRay> = Puzzle::make_solved(vec![[-2, 2], [0, 0], [2, -2]]);
+        let selected = |_| vec![[9, 9]];
+        let twist = |face: &str, direction, layers: Vec<usize>| KeyAction::Twist {
+            face: face.to_string(),
+            direction,
+            layers,
+        };
+
+        assert_eq!(
+            twist("L", 1, vec![]).twist(&puzzle, &[], selected).unwrap(),
+            Some(((L, -1), vec![[9, 9]]))
+        );
+        assert_eq!(
+            twist("R", -1, vec![1, 2])
+                .twist(&puzzle, &[], selected)
+                .unwrap(),
+            Some(((R, -1), vec![[2, -2], [0, 0]]))
+        );
+        // layer modifiers
+        assert_eq!(
+            twist("U", 1, vec![])
+                .twist(&puzzle, &[2], selected)
+                .unwrap(),
+            Some(((U, 1), vec![[0, 0]]))
+        );
+        assert!(twist("U", 1, vec![4])
+            .twist(&puzzle, &[], selected)
+            .is_err());
+        assert!(twist("Q", 1, vec![]).twist(&puzzle, &[], selected).is_err());
+        assert_eq!(
+            KeyAction::Layer(1).twist(&puzzle, &[], selected).unwrap(),
+            None
+        );
+        assert_eq!(
+            KeyAction::Rotate {
+                face: "F".to_string(),
+                direction: 1
+            }
+            .twist(&puzzle, &[], selected)
+            .unwrap()
+            .map(|(turn, grips)| (turn, grips.len())),
+            Some(((F, -1), 3))
+        );
+    }
+}
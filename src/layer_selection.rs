@@ -0,0 +1,179 @@
+use std::collections::{BTreeSet, HashMap};
+use three_d::{Key, Modifiers};
+
+const DIGIT_KEYS: [Key; 10] = [
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+    Key::Num0,
+];
+
+/// How many layers the digit keys reach without modifiers, shift adds this once
+/// and alt adds it twice.
+const LAYERS_PER_MODIFIER: usize = DIGIT_KEYS.len();
+
+/// The layer selected by pressing the key with the modifiers, 1-indexed.
+/// `1` to `9` and `0` select layers 1 to 10, shift adds 10 and alt adds 20.
+pub fn key_layer(key: Key, modifiers: &Modifiers) -> Option<usize> {
+    let digit = DIGIT_KEYS.iter().position(|&k| k == key)?;
+    let offset = usize::from(modifiers.shift) + 2 * usize::from(modifiers.alt);
+    Some(digit + 1 + offset * LAYERS_PER_MODIFIER)
+}
+
+/// The name of the key combination that selects the layer.
+pub fn layer_key_name(layer: usize) -> String {
+    let (offset, digit) = (
+        (layer - 1) / LAYERS_PER_MODIFIER,
+        (layer - 1) % LAYERS_PER_MODIFIER,
+    );
+    let digit = (digit + 1) % 10;
+    match offset {
+        0 => digit.to_string(),
+        1 => format!("⇧{digit}"),
+        2 => format!("⎇{digit}"),
+        _ => format!("⇧⎇{digit}"),
+    }
+}
+
+/// The layers selected for twisting, by held keys and by clicking.
+/// Layers are 1-indexed and counted from the head of the axis.
+#[derive(Default)]
+pub struct LayerSelection {
+    /// The layer selected by each held digit key.
+    /// The modifiers can change while the key is held, so the layer is remembered.
+    held: HashMap<Key, usize>,
+    clicked: BTreeSet<usize>,
+    /// The layer last toggled by clicking, where ranges start from
+    anchor: Option<usize>,
+}
+
+impl LayerSelection {
+    /// Holds the layer of the key. Returns whether the key selects a layer.
+    pub fn press(&mut self, key: Key, modifiers: &Modifiers) -> bool {
+        match key_layer(key, modifiers) {
+            Some(layer) => {
+                self.held.insert(key, layer);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn release(&mut self, key: Key) {
+        self.held.remove(&key);
+    }
+
+    pub fn is_held(&self, layer: usize) -> bool {
+        self.held.values().any(|&l| l == layer)
+    }
+
+    pub fn is_clicked(&self, layer: usize) -> bool {
+        self.clicked.contains(&layer)
+    }
+
+    pub fn toggle(&mut self, layer: usize) {
+        if !self.clicked.remove(&layer) {
+            self.clicked.insert(layer);
+        }
+        self.anchor = Some(layer);
+    }
+
+    /// Clicks every layer from the last toggled one to this one, for slice moves.
+    pub fn select_range(&mut self, layer: usize) {
+        let anchor = self.anchor.unwrap_or(layer);
+        self.clicked = (anchor.min(layer)..=anchor.max(layer)).collect();
+    }
+
+    /// Clicks every layer from the first to the deepest selected one, for wide moves.
+    pub fn widen(&mut self) {
+        let deepest = self.selected().last().copied().unwrap_or(1);
+        self.clicked.extend(1..=deepest);
+    }
+
+    pub fn clear(&mut self) {
+        self.clicked.clear();
+        self.anchor = None;
+    }
+
+    /// Replaces the clicked layers, like with a typed range.
+    pub fn set_clicked(&mut self, layers: impl IntoIterator<Item = usize>) {
+        self.clicked = layers.into_iter().collect();
+    }
+
+    /// All selected layers, in order.
+    pub fn selected(&self) -> Vec<usize> {
+        let mut selected: BTreeSet<usize> = self.held.values().copied().collect();
+        selected.extend(&self.clicked);
+        selected.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layer_keys() {
+        let none = Modifiers::default();
+        let shift = Modifiers {
+            shift: true,
+            ..Default::default()
+        };
+        let alt = Modifiers {
+            alt: true,
+            ..Default::default()
+        };
+        assert_eq!(key_layer(Key::Num1, &none), Some(1));
+        assert_eq!(key_layer(Key::Num0, &none), Some(10));
+        assert_eq!(key_layer(Key::Num7, &shift), Some(17));
+        assert_eq!(key_layer(Key::Num0, &alt), Some(30));
+        assert_eq!(key_layer(Key::A, &none), None);
+        for layer in 1..=30 {
+            assert!(!layer_key_name(layer).is_empty());
+        }
+        assert_eq!(layer_key_name(10), "0");
+        assert_eq!(layer_key_name(17), "⇧7");
+    }
+
+    #[test]
+    fn selection() {
+        let shift = Modifiers {
+            shift: true,
+            ..Default::default()
+        };
+        let mut selection = LayerSelection::default();
+        selection.press(Key::Num2, &shift);
+        selection.toggle(3);
+        selection.toggle(5);
+        selection.toggle(3);
+        assert_eq!(selection.selected(), vec![5, 12]);
+        // releasing without shift still releases layer 12
+        selection.release(Key::Num2);
+        assert_eq!(selection.selected(), vec![5]);
+        selection.set_clicked([2, 3]);
+        assert_eq!(selection.selected(), vec![2, 3]);
+    }
+
+    #[test]
+    fn ranges() {
+        let mut selection = LayerSelection::default();
+        selection.toggle(5);
+        selection.select_range(2);
+        assert_eq!(selection.selected(), vec![2, 3, 4, 5]);
+        selection.widen();
+        assert_eq!(selection.selected(), vec![1, 2, 3, 4, 5]);
+        selection.clear();
+        selection.widen();
+        assert_eq!(selection.selected(), vec![1]);
+        // without an anchor only the layer is selected
+        selection.clear();
+        selection.select_range(3);
+        assert_eq!(selection.selected(), vec![3]);
+    }
+}
@@ -0,0 +1,542 @@
+use crate::puzzle::common::{Grip, AXIS_RAYS};
+use crate::session::SessionLog;
+use eyre::eyre;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::iter::zip;
+
+/// The layout of `SessionLog` written by this version of laminated.
+/// Logs with an older layout are brought up to date by `MIGRATIONS` when loading.
+pub const LOG_SCHEMA: u32 = 2;
+
+/// `MIGRATIONS[i]` turns a log with schema `i + 1` into one with schema `i + 2`.
+/// Logs from before schemas have the layout of schema 1.
+/// Never change these once released, add a new one instead.
+const MIGRATIONS: [fn(&mut Value) -> eyre::Result<()>; LOG_SCHEMA as usize - 1] = [migrate_1_to_2];
+
+/// The ray names of each kind of puzzle in the order of schema 1 orientations,
+/// which was the order of the ray enums then.
+fn schema_1_ray_order(session_type: &Value) -> eyre::Result<&'static [&'static str]> {
+    let family = session_type
+        .as_object()
+        .and_then(|session_type| session_type.keys().next());
+    match family.map(String::as_str) {
+        Some("Cube") => Ok(&["R", "B", "U", "L", "F", "D"]),
+        Some("Octa") => Ok(&["BU", "BL", "U", "L", "BR", "D", "R", "F"]),
+        Some("Dodeca") => Ok(&[
+            "PB", "BR", "PR", "U", "DR", "PL", "PD", "BL", "R", "F", "DL", "L",
+        ]),
+        Some("RDodeca") => Ok(&[
+            "BU", "UR", "RB", "FU", "DR", "LB", "BD", "UL", "RF", "FD", "DL", "LF",
+        ]),
+        _ => Err(eyre!("Invalid session type")),
+    }
+}
+
+/// Schema 2 keys orientations by ray name instead of listing them in enum order,
+/// so reordering the rays does not change what old logs mean.
+fn migrate_1_to_2(log: &mut Value) -> eyre::Result<()> {
+    let order = schema_1_ray_order(&log["session_type"])?;
+    let scramble = log["scramble"]
+        .as_array_mut()
+        .ok_or_else(|| eyre!("Log has no scramble"))?;
+    for ori in scramble {
+        let occupants = ori
+            .as_array()
+            .filter(|occupants| occupants.len() == order.len())
+            .ok_or_else(|| eyre!("Invalid orientation in log"))?;
+        let named: serde_json::Map<String, Value> = zip(order, occupants)
+            .map(|(ray, occupant)| (ray.to_string(), occupant.clone()))
+            .collect();
+        *ori = named.into();
+    }
+    Ok(())
+}
+
+/// Brings a log up to the current schema.
+fn migrate(mut log: Value) -> eyre::Result<SessionLog> {
+    if !log.is_object() {
+        return Err(eyre!("Invalid log"));
+    }
+    let schema = match log.get("schema") {
+        None => 1,
+        Some(schema) => schema
+            .as_u64()
+            .filter(|&schema| schema >= 1)
+            .ok_or_else(|| eyre!("Invalid log schema"))? as usize,
+    };
+    if schema > LOG_SCHEMA as usize {
+        return Err(eyre!(
+            "Log schema {schema} is newer than this version of laminated"
+        ));
+    }
+    for migration in &MIGRATIONS[schema - 1..] {
+        migration(&mut log)?;
+    }
+    log["schema"] = LOG_SCHEMA.into();
+    Ok(serde_json::from_value(log)?)
+}
+
+/// The first line of a compact log, followed by the format version.
+const COMPACT_MAGIC: &str = "laminated-compact-log";
+/// The version of the compact layout written by this version of laminated.
+const COMPACT_VERSION: u32 = 2;
+
+/// How a log file is written. Loading detects the format by itself.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// Readable JSON
+    #[default]
+    Json,
+    /// The scramble and twists packed into base64, for big puzzles
+    Compact,
+}
+
+impl LogFormat {
+    pub const ALL: [Self; 2] = [Self::Json, Self::Compact];
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Json => write!(f, "JSON"),
+            Self::Compact => write!(f, "Compact"),
+        }
+    }
+}
+
+/// The format of a log file's bytes. Anything that is not compact is taken to be JSON.
+pub fn detect(bytes: &[u8]) -> LogFormat {
+    if bytes.starts_with(COMPACT_MAGIC.as_bytes()) {
+        LogFormat::Compact
+    } else {
+        LogFormat::Json
+    }
+}
+
+pub fn encode(log: &SessionLog, format: LogFormat) -> eyre::Result<Vec<u8>> {
+    match format {
+        LogFormat::Json => Ok(serde_json::to_vec(log)?),
+        LogFormat::Compact => {
+            let packed = base64_encode(&pack(log)?);
+            Ok(format!("{COMPACT_MAGIC} {COMPACT_VERSION}\n{packed}\n").into_bytes())
+        }
+    }
+}
+
+/// Whether the bytes look like a log in either format rather than some other file.
+/// JSON logs are told apart by having a `session_type`, which means parsing all of the JSON.
+pub fn looks_like_log(bytes: &[u8]) -> bool {
+    bytes.starts_with(COMPACT_MAGIC.as_bytes())
+        || serde_json::from_slice::<Value>(bytes).is_ok_and(|log| log.get("session_type").is_some())
+}
+
+/// Reads a log in either format.
+pub fn decode(bytes: &[u8]) -> eyre::Result<SessionLog> {
+    let Some(rest) = bytes.strip_prefix(COMPACT_MAGIC.as_bytes()) else {
+        return migrate(serde_json::from_slice(bytes)?);
+    };
+    let text = std::str::from_utf8(rest)?;
+    let (version, packed) = text
+        .split_once('\n')
+        .ok_or_else(|| eyre!("Compact log has no contents"))?;
+    let version: u32 = version
+        .trim()
+        .parse()
+        .map_err(|_| eyre!("Invalid compact log version"))?;
+    if version > COMPACT_VERSION {
+        return Err(eyre!(
+            "Compact log version {version} is newer than this version of laminated"
+        ));
+    }
+    migrate(unpack(&base64_decode(packed)?, version)?)
+}
+
+/// The binary layout, with every number a LEB128 varint unless noted:
+/// - the length of the JSON of everything but the scramble and twists, then that JSON
+/// - the ray names used, each as a length and UTF-8 bytes, referred to by index below
+/// - the distinct orientations, each as a count and pairs of ray indices,
+///   a ray and the ray occupying it. Version 1 had the occupying rays in enum order.
+/// - the scramble, as a count and an orientation index for each piece
+/// - 1 and the scramble positions as a count and increases, or 0 if there are none
+/// - the twists, as a count and for each the ray index, the order as a byte,
+///   and the grips as a count and `AXIS_RAYS` bytes each
+fn pack(log: &SessionLog) -> eyre::Result<Vec<u8>> {
+    let metadata = SessionLog {
+        version: log.version.clone(),
+        schema: log.schema,
+        session_type: log.session_type,
+        scramble: vec![],
+        scramble_positions: None,
+        twists: vec![],
+        move_count: log.move_count,
+        blindfold: log.blindfold,
+        camera: log.camera.clone(),
+    };
+    let metadata = serde_json::to_vec(&metadata)?;
+
+    let mut names = Interner::default();
+    let mut orientations = Interner::default();
+    let scramble: Vec<usize> = log
+        .scramble
+        .iter()
+        .map(|ori| {
+            let rays: Vec<(usize, usize)> = ori
+                .iter()
+                .map(|(ray, occupant)| (names.index(ray), names.index(occupant)))
+                .collect();
+            orientations.index(&rays)
+        })
+        .collect();
+    let twist_rays: Vec<usize> = log
+        .twists
+        .iter()
+        .map(|((name, _), _)| names.index(name))
+        .collect();
+
+    let mut out = vec![];
+    write_varint(&mut out, metadata.len());
+    out.extend(&metadata);
+
+    write_varint(&mut out, names.values.len());
+    for name in &names.values {
+        write_varint(&mut out, name.len());
+        out.extend(name.as_bytes());
+    }
+    write_varint(&mut out, orientations.values.len());
+    for rays in &orientations.values {
+        write_varint(&mut out, rays.len());
+        for &(ray, occupant) in rays {
+            write_varint(&mut out, ray);
+            write_varint(&mut out, occupant);
+        }
+    }
+    write_varint(&mut out, scramble.len());
+    for ori in scramble {
+        write_varint(&mut out, ori);
+    }
+
+    match &log.scramble_positions {
+        Some(positions) => {
+            out.push(1);
+            write_varint(&mut out, positions.len());
+            let mut last = 0;
+            for &position in positions {
+                let increase = position
+                    .checked_sub(last)
+                    .ok_or_else(|| eyre!("Scramble positions are not in order"))?;
+                write_varint(&mut out, increase);
+                last = position;
+            }
+        }
+        None => out.push(0),
+    }
+
+    write_varint(&mut out, log.twists.len());
+    for (((_, order), grips), ray) in log.twists.iter().zip(twist_rays) {
+        write_varint(&mut out, ray);
+        out.push(*order as u8);
+        write_varint(&mut out, grips.len());
+        for grip in grips {
+            out.extend(grip.map(|layer| layer as u8));
+        }
+    }
+    Ok(out)
+}
+
+/// Reads the binary layout of `pack` into the JSON of the log, which might need migrating.
+fn unpack(bytes: &[u8], version: u32) -> eyre::Result<Value> {
+    let mut reader = Reader { bytes, pos: 0 };
+
+    let metadata_len = reader.varint()?;
+    let mut log: Value = serde_json::from_slice(reader.take(metadata_len)?)?;
+    if !log.is_object() {
+        return Err(eyre!("Invalid compact log"));
+    }
+
+    let names = (0..reader.varint()?)
+        .map(|_| {
+            let len = reader.varint()?;
+            Ok(std::str::from_utf8(reader.take(len)?)?.to_string())
+        })
+        .collect::<eyre::Result<Vec<String>>>()?;
+    let name = |index: usize| {
+        names
+            .get(index)
+            .cloned()
+            .ok_or_else(|| eyre!("Invalid ray in compact log"))
+    };
+    let orientations = (0..reader.varint()?)
+        .map(|_| {
+            let count = reader.varint()?;
+            if version == 1 {
+                let occupants = (0..count)
+                    .map(|_| name(reader.varint()?))
+                    .collect::<eyre::Result<Vec<String>>>()?;
+                return Ok(Value::from(occupants));
+            }
+            let named = (0..count)
+                .map(|_| Ok((name(reader.varint()?)?, name(reader.varint()?)?.into())))
+                .collect::<eyre::Result<serde_json::Map<String, Value>>>()?;
+            Ok(Value::from(named))
+        })
+        .collect::<eyre::Result<Vec<Value>>>()?;
+    let scramble: Vec<Value> = (0..reader.varint()?)
+        .map(|_| {
+            orientations
+                .get(reader.varint()?)
+                .cloned()
+                .ok_or_else(|| eyre!("Invalid orientation in compact log"))
+        })
+        .collect::<eyre::Result<_>>()?;
+
+    let scramble_positions: Option<Vec<usize>> = match reader.byte()? {
+        0 => None,
+        _ => {
+            let mut last = 0;
+            Some(
+                (0..reader.varint()?)
+                    .map(|_| {
+                        last += reader.varint()?;
+                        Ok(last)
+                    })
+                    .collect::<eyre::Result<_>>()?,
+            )
+        }
+    };
+
+    let twists: Vec<((String, i8), Vec<Grip>)> = (0..reader.varint()?)
+        .map(|_| {
+            let ray = name(reader.varint()?)?;
+            let order = reader.byte()? as i8;
+            let grips = (0..reader.varint()?)
+                .map(|_| {
+                    let layers = reader.take(AXIS_RAYS)?;
+                    Ok(<[u8; AXIS_RAYS]>::try_from(layers)?.map(|layer| layer as i8))
+                })
+                .collect::<eyre::Result<_>>()?;
+            Ok(((ray, order), grips))
+        })
+        .collect::<eyre::Result<_>>()?;
+
+    log["scramble"] = serde_json::to_value(scramble)?;
+    log["scramble_positions"] = serde_json::to_value(scramble_positions)?;
+    log["twists"] = serde_json::to_value(twists)?;
+    Ok(log)
+}
+
+/// Gives each distinct value an index, in order of first appearance.
+struct Interner<T> {
+    values: Vec<T>,
+    indices: HashMap<T, usize>,
+}
+
+impl<T> Default for Interner<T> {
+    fn default() -> Self {
+        Self {
+            values: vec![],
+            indices: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Clone + Eq + std::hash::Hash> Interner<T> {
+    fn index<Q: ToOwned<Owned = T> + ?Sized>(&mut self, value: &Q) -> usize {
+        let value = value.to_owned();
+        if let Some(&index) = self.indices.get(&value) {
+            return index;
+        }
+        self.values.push(value.clone());
+        self.indices.insert(value, self.values.len() - 1);
+        self.values.len() - 1
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut n: usize) {
+    while n >= 0x80 {
+        out.push((n & 0x7f) as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> eyre::Result<&'a [u8]> {
+        let taken = self
+            .pos
+            .checked_add(len)
+            .and_then(|end| self.bytes.get(self.pos..end))
+            .ok_or_else(|| eyre!("Compact log ends early"))?;
+        self.pos += len;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> eyre::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn varint(&mut self) -> eyre::Result<usize> {
+        let mut n = 0usize;
+        for shift in (0..usize::BITS).step_by(7) {
+            let byte = self.byte()?;
+            n |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(eyre!("Invalid number in compact log"))
+    }
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Decodes base64, ignoring whitespace.
+fn base64_decode(text: &str) -> eyre::Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() / 4 * 3);
+    let mut n = 0u32;
+    let mut bits = 0;
+    for c in text
+        .bytes()
+        .filter(|c| !c.is_ascii_whitespace() && *c != b'=')
+    {
+        let value = BASE64_ALPHABET
+            .iter()
+            .position(|&a| a == c)
+            .ok_or_else(|| eyre!("Invalid character in compact log"))?;
+        n = n << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((n >> bits) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzle::common::NamedOrientation;
+    use crate::session::{CubePuzzle, SessionType};
+
+    fn example_log() -> SessionLog {
+        let named = |occupants: [&str; 6]| -> NamedOrientation {
+            zip(["R", "B", "U", "L", "F", "D"], occupants)
+                .map(|(ray, occupant)| (ray.to_string(), occupant.to_string()))
+                .collect()
+        };
+        let solved = named(["R", "B", "U", "L", "F", "D"]);
+        let turned = named(["F", "R", "U", "B", "L", "D"]);
+        SessionLog {
+            version: "0.4.0".to_string(),
+            schema: LOG_SCHEMA,
+            session_type: SessionType::Cube(CubePuzzle::Nnn(3)),
+            scramble: vec![solved.clone(), turned, solved],
+            scramble_positions: Some(vec![0, 2, 300]),
+            twists: vec![
+                (("R".to_string(), 1), vec![[2, -2]]),
+                (("U".to_string(), -1), vec![[-2, 2], [0, 0]]),
+            ],
+            move_count: None,
+            blindfold: None,
+            camera: None,
+        }
+    }
+
+    #[test]
+    fn compact_round_trip() {
+        let log = example_log();
+        let compact = encode(&log, LogFormat::Compact).unwrap();
+        assert!(compact.starts_with(b"laminated-compact-log 2\n"));
+        assert_eq!(detect(&compact), LogFormat::Compact);
+        let decoded = decode(&compact).unwrap();
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            serde_json::to_value(&log).unwrap()
+        );
+
+        let json = encode(&log, LogFormat::Json).unwrap();
+        assert_eq!(detect(&json), LogFormat::Json);
+        let decoded = decode(&json).unwrap();
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            serde_json::to_value(&log).unwrap()
+        );
+    }
+
+    #[test]
+    fn compact_is_small() {
+        let mut log = example_log();
+        log.scramble = log.scramble.into_iter().cycle().take(1000).collect();
+        log.scramble_positions = None;
+        let compact = encode(&log, LogFormat::Compact).unwrap();
+        let json = encode(&log, LogFormat::Json).unwrap();
+        assert!(compact.len() * 10 < json.len());
+    }
+
+    #[test]
+    fn bad_compact_logs() {
+        assert!(decode(b"laminated-compact-log 3\nAAAA\n").is_err());
+        assert!(decode(b"laminated-compact-log 1\nAA!A\n").is_err());
+        let compact = encode(&example_log(), LogFormat::Compact).unwrap();
+        assert!(decode(&compact[..compact.len() - 8]).is_err());
+    }
+
+    #[test]
+    fn schema_1_orientations() {
+        let log = br#"{"version":"0.4.0","schema":1,"session_type":{"Cube":{"Nnn":3}},
+            "scramble":[["F","R","U","B","L","D"]],"twists":[]}"#;
+        let log = decode(log).unwrap();
+        assert_eq!(log.schema, LOG_SCHEMA);
+        assert_eq!(log.scramble, example_log().scramble[1..2]);
+
+        let wrong_length = br#"{"version":"0.4.0","schema":1,"session_type":{"Cube":{"Nnn":3}},
+            "scramble":[["F","R","U","B","L"]],"twists":[]}"#;
+        assert!(decode(wrong_length).is_err());
+
+        let unversioned = br#"{"version":"0.4.0","session_type":{"Cube":{"Nnn":3}},
+            "scramble":[["F","R","U","B","L","D"]],"twists":[]}"#;
+        assert_eq!(decode(unversioned).unwrap().scramble, log.scramble);
+
+        let schema_0 = br#"{"version":"0.4.0","schema":0,"session_type":{"Cube":{"Nnn":3}},
+            "scramble":[],"twists":[]}"#;
+        assert!(decode(schema_0).is_err());
+    }
+
+    #[test]
+    fn base64() {
+        for bytes in [&b""[..], b"a", b"ab", b"abc", b"abcd", &[0, 255, 128, 7]] {
+            let encoded = base64_encode(bytes);
+            assert_eq!(encoded.len() % 4, 0);
+            assert_eq!(base64_decode(&encoded).unwrap(), bytes);
+        }
+        assert_eq!(base64_encode(b"laminated"), "bGFtaW5hdGVk");
+    }
+}
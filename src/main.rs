@@ -1,41 +1,56 @@
+use crate::autosave::{Autosave, AutosaveFile};
 use crate::key_label::*;
-use crate::preferences::Preferences;
+use crate::keybinds::{held_layers, load_keybinds, save_keybinds, KeyAction, Keybind};
+use crate::layer_selection::{layer_key_name, LayerSelection};
+use crate::log_format::LogFormat;
+use crate::preferences::{Macro, Preferences, PREFS_PATH};
+use crate::puzzle::common::{Grip, Twist};
 use crate::puzzle::cube::CubeRay;
 use crate::puzzle::dodeca::DodecaRay;
+use crate::puzzle::filter::{FilterStyle, SolvedFilter};
+use crate::puzzle::notation::{self, parse_layer_list};
 use crate::puzzle::octa::OctaRay;
 use crate::puzzle::r_dodeca::RDodecaRay;
+use crate::puzzle::symmetry::{turns_moving, Symmetry};
 use crate::render::common::*;
 use crate::render::create::*;
 use crate::session::*;
-use crate::util::enum_iter;
+use crate::util::key_names::{key_from_name, key_name};
+use crate::util::{color, enum_iter};
 use eyre::eyre;
 
 use std::collections::HashSet;
 
 use three_d::*;
 
+pub mod args;
+pub mod autosave;
 pub mod key_label;
+pub mod keybinds;
+pub mod layer_selection;
+pub mod log_format;
 pub mod preferences;
 pub mod puzzle;
 pub mod render;
 pub mod session;
 pub mod util;
+pub mod window;
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 const TURN_DISTANCE_THRESHOLD: f32 = 3.0;
 const ORBIT_SPEED: f32 = 0.007; // radians per pixel
+const ZOOM_SPEED: f32 = 0.004; // zoom is multiplied by e to this per scrolled pixel
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 8.0;
 const ANIMATION_INIT_V: f32 = 0.1;
-const NUMBER_KEYS: [Key; 9] = [
-    Key::Num1,
-    Key::Num2,
-    Key::Num3,
-    Key::Num4,
-    Key::Num5,
-    Key::Num6,
-    Key::Num7,
-    Key::Num8,
-    Key::Num9,
-]; // has to be an array?
+/// Stickers of pieces other than the inspected one fade towards this color
+const INSPECTOR_FADE_COLOR: color::Color = color::Color::hex(0xcccccc);
+const FILTER_GRAY_COLOR: color::Color = color::Color::hex(0x808080);
+const FILTER_TRANSLUCENT_ALPHA: u8 = 40;
+/// Stickers in the layers under the mouse are tinted with this color
+const HOVER_COLOR: color::Color = color::WHITE;
+/// Every sticker has this color while solving blindfolded
+const BLINDFOLD_COLOR: color::Color = color::Color::hex(0x707070);
 
 fn get_viewport_from_pixel<Ray: ConcreteRaySystem>(
     concrete_puzzle: &ConcretePuzzle<Ray>,
@@ -57,36 +72,220 @@ fn get_viewport_from_pixel<Ray: ConcreteRaySystem>(
     None
 }
 
+/// Finds the viewport and the sticker under the pixel.
+fn sticker_at_pixel<Ray: ConcreteRaySystem>(
+    session: &Session<Ray>,
+    position: LogicalPoint,
+) -> Option<(&PuzzleViewport<Ray>, &Sticker<Ray>)> {
+    let viewport = get_viewport_from_pixel(&session.concrete_puzzle, position)?;
+    let camera = viewport.make_camera(&session.camera_facings[viewport.conjugate]);
+    let sticker = viewport.ray_intersect(
+        camera.position_at_pixel(position),
+        camera.view_direction_at_pixel(position),
+    )?;
+    Some((viewport, sticker))
+}
+
+/// The grips that clicking a sticker on the face turns with the selected layers.
+fn selected_grips<Ray: ConcreteRaySystem>(
+    concrete_puzzle: &ConcretePuzzle<Ray>,
+    viewport: &PuzzleViewport<Ray>,
+    layers: &[usize],
+    viewport_keys: bool,
+    face: Ray,
+) -> Vec<Grip> {
+    let axis_index = face.axis_index();
+
+    if layers.is_empty() {
+        viewport.key_layers[axis_index]
+            .first()
+            .map_or(vec![], |layer| vec![*layer])
+    } else {
+        let key_layers = if viewport_keys {
+            &viewport.key_layers
+        } else {
+            &concrete_puzzle.key_layers
+        };
+        // if a layer the puzzle does not have is selected,
+        // it turns nothing instead of doing the default turn
+        layers
+            .iter()
+            .filter_map(|layer| key_layers[axis_index].get(layer.checked_sub(1)?))
+            .cloned()
+            .collect()
+    }
+}
+
+/// The twist done by dragging the mouse along the positions, starting on a sticker.
+/// The drag turns every layer between the first and last one it went over.
+fn drag_twist<Ray: ConcreteRaySystem>(
+    session: &Session<Ray>,
+    positions: &[LogicalPoint],
+) -> Option<Twist<Ray>> {
+    let (&first, &last) = (positions.first()?, positions.last()?);
+    let (viewport, sticker) = sticker_at_pixel(session, first)?;
+    let camera = viewport.make_camera(&session.camera_facings[viewport.conjugate]);
+    let (start, end): (PhysicalPoint, PhysicalPoint) = (first.into(), last.into());
+    let (ray, order) =
+        viewport.drag_turn(&camera, sticker, vec2(end.x - start.x, end.y - start.y))?;
+
+    let puzzle = &session.concrete_puzzle.puzzle;
+    let grips = puzzle.grips_from_face(ray);
+    let crossed: Vec<usize> = positions
+        .iter()
+        .filter_map(|&position| {
+            let (over_viewport, over_sticker) = sticker_at_pixel(session, position)?;
+            if !std::ptr::eq(over_viewport, viewport) {
+                return None;
+            }
+            let grip = puzzle
+                .piece_by_ind(over_sticker.piece_ind)
+                .grip_on_axis(ray);
+            grips.iter().position(|g| *g == grip)
+        })
+        .collect();
+    let (&min, &max) = (crossed.iter().min()?, crossed.iter().max()?);
+    Some(((ray, order), grips[min..=max].to_vec()))
+}
+
+/// Does what the key is bound to, or returns None if it is not bound.
+fn do_keybind<Ray: ConcreteRaySystem>(
+    session: &mut Session<Ray>,
+    keybinds: &[Keybind],
+    (key, shift): (Key, bool),
+    keys: &HashSet<Key>,
+    layers: &[usize],
+    prefs: &Preferences,
+) -> Option<eyre::Result<()>> {
+    let bind = keybinds.iter().find(|bind| bind.matches(key, shift))?;
+    let held = held_layers(keybinds, keys.iter().copied());
+    let concrete_puzzle = &session.concrete_puzzle;
+    let twist = bind.action.twist(&concrete_puzzle.puzzle, &held, |face| {
+        selected_grips(
+            concrete_puzzle,
+            &concrete_puzzle.viewports[0],
+            layers,
+            prefs.viewport_keys,
+            face,
+        )
+    });
+    Some(twist.map(|twist| {
+        if let Some((turn, grips)) = twist {
+            session.twist(turn, grips, prefs.animation_length);
+        }
+    }))
+}
+
 fn render_puzzle<Ray: ConcreteRaySystem>(
     screen: &mut RenderTarget,
+    context: &Context,
     elapsed_time: f64,
-    concrete_puzzle: &mut ConcretePuzzle<Ray>,
+    session: &mut Session<Ray>,
     prefs: &Preferences,
-    facings: &enum_map::EnumMap<Ray::Conjugate, CameraFacing>,
 ) {
-    screen.clear(ClearState::color_and_depth(0.8, 0.8, 0.8, 1.0, 1.0));
-
-    let permutation = concrete_puzzle.puzzle.permutation();
-
+    let colors_hidden = session.colors_hidden();
+    let concrete_puzzle = &mut session.concrete_puzzle;
+    let facings = &session.camera_facings;
+    let inspected_piece = session.inspected_piece;
+    let piece_types = &session.piece_types;
+    let filter = session.piece_filter.as_ref();
+    let hovered_layers = session.hovered_layers.as_ref();
+
+    let background = prefs.visuals.background;
+    screen.clear(ClearState::color_and_depth(
+        background.r as f32 / 255.0,
+        background.g as f32 / 255.0,
+        background.b as f32 / 255.0,
+        1.0,
+        1.0,
+    ));
+
+    let look = ViewportLook {
+        colors: Ray::ray_to_color(prefs).values().copied().collect(),
+        colors_hidden,
+        inspected_piece,
+        filter: filter.cloned(),
+    };
     for viewport in &mut concrete_puzzle.viewports.iter_mut() {
-        let camera = viewport.make_camera(&facings[viewport.conjugate]);
-
-        screen.render(
-            &camera,
-            viewport.stickers.iter_mut().map(|sticker| {
-                let puzzle = &concrete_puzzle.puzzle;
-                let piece_at_sticker = puzzle.piece_by_ind(sticker.piece_ind, &permutation);
-                sticker.update_gm(
-                    Ray::ray_to_color(prefs)[piece_at_sticker.orientation[sticker.color]]
-                        .to_srgba(),
-                    elapsed_time as f32,
-                    prefs.animation_length,
-                );
+        let facing = &facings[viewport.conjugate];
+        let camera = viewport.make_camera(facing);
+        // the light comes from a bit above the camera
+        let light = prefs.visuals.lighting.then(|| {
+            ((facing.position - facing.target).normalize() + facing.screen_up() * 0.5).normalize()
+        });
+
+        let puzzle = &concrete_puzzle.puzzle;
+        if viewport.mesh.is_none() {
+            viewport.mesh = Some(ViewportMesh::new(context, &viewport.stickers));
+            // a new mesh has none of the stickers written in it
+            for sticker in viewport.stickers.iter_mut() {
+                sticker.drawn = None;
+            }
+            viewport.look = None;
+        }
 
-                &sticker.gm
-            }),
-            &[],
-        );
+        // only the stickers that may look different are updated
+        if viewport.hovered_layers.as_ref() != hovered_layers {
+            if let Some(layers) = viewport.hovered_layers.take() {
+                viewport.mark_layers(puzzle, &layers);
+            }
+            if let Some(layers) = hovered_layers {
+                viewport.mark_layers(puzzle, layers);
+            }
+            viewport.hovered_layers = hovered_layers.cloned();
+        }
+        let mut dirty = std::mem::take(&mut viewport.dirty);
+        if viewport.look.as_ref() != Some(&look) {
+            dirty = (0..viewport.stickers.len()).collect();
+            viewport.look = Some(look.clone());
+        }
+        dirty.sort_unstable();
+        dirty.dedup();
+
+        let mut changed = vec![];
+        for i in dirty {
+            let sticker = &mut viewport.stickers[i];
+            let piece_index = puzzle.piece_index_by_ind(sticker.piece_ind);
+            let piece_at_sticker = &puzzle.pieces[piece_index];
+            let (srgba, visible) = if colors_hidden {
+                (BLINDFOLD_COLOR.to_srgba(), true)
+            } else {
+                let mut color =
+                    Ray::ray_to_color(prefs)[piece_at_sticker.orientation[sticker.color]];
+                // fade everything except the inspected piece
+                if inspected_piece.is_some_and(|inspected| inspected != piece_index) {
+                    color = color.mix(INSPECTOR_FADE_COLOR, 0.75);
+                }
+                if hovered_layers.is_some_and(|(face, grips)| {
+                    grips.contains(&piece_at_sticker.grip_on_axis(*face))
+                }) {
+                    color = color.mix(HOVER_COLOR, 0.35);
+                }
+                let style = filter
+                    .filter(|filter| !filter.passes(piece_at_sticker, &piece_types[piece_index]))
+                    .map(|filter| filter.style);
+                let mut srgba = color.to_srgba();
+                match style {
+                    Some(FilterStyle::Gray) => {
+                        srgba = color.mix(FILTER_GRAY_COLOR, 0.8).to_srgba();
+                    }
+                    Some(FilterStyle::Translucent) => srgba.a = FILTER_TRANSLUCENT_ALPHA,
+                    Some(FilterStyle::Hide) | None => (),
+                }
+                (srgba, style != Some(FilterStyle::Hide))
+            };
+            // hidden stickers still have to be updated so their animations finish
+            if sticker.update(srgba, visible, elapsed_time as f32, prefs.animation_length) {
+                changed.push(i);
+            }
+            if sticker.animation.is_some() {
+                viewport.dirty.push(i);
+            }
+        }
+        if let Some(mesh) = &viewport.mesh {
+            mesh.write_stickers(&viewport.stickers, &changed);
+            screen.write(|| mesh.render(&camera, light));
+        }
     }
 }
 
@@ -154,6 +353,173 @@ fn color_picker_grid<Ray: ConcreteRaySystem>(
     });
 }
 
+fn keybind_file_dialog() -> rfd::FileDialog {
+    rfd::FileDialog::new()
+        .add_filter("Keybind files", &["keybinds"])
+        .add_filter("All files", &["*"])
+}
+
+/// Editor for the keybinds of the puzzle family in the settings panel.
+fn keybind_editor<Ray: ConcreteRaySystem>(
+    ui: &mut egui::Ui,
+    prefs: &mut Preferences,
+    family: &'static str,
+    status_message: &mut Option<String>,
+) {
+    use egui::*;
+
+    ui.horizontal(|ui| {
+        if ui.button("Import").clicked() {
+            if let Some(path) = keybind_file_dialog().pick_file() {
+                match load_keybinds(&path) {
+                    Ok(keybinds) => *prefs.keybinds_mut(family) = keybinds,
+                    Err(err) => *status_message = Some(err.to_string()),
+                }
+            }
+        }
+        if ui.button("Export").clicked() {
+            if let Some(path) = keybind_file_dialog()
+                .set_file_name(format!("{family}.keybinds"))
+                .save_file()
+            {
+                *status_message = save_keybinds(&path, &prefs.keybinds(family))
+                    .err()
+                    .map(|err| err.to_string());
+            }
+        }
+        if ui.button("Reset").clicked() {
+            prefs.keybinds.remove(family);
+        }
+    });
+
+    let keybinds = prefs.keybinds_mut(family);
+    let mut delete = None;
+    Grid::new(format!("{family}_keybind_grid"))
+        .min_col_width(0.0)
+        .striped(true)
+        .show(ui, |ui| {
+            for (i, bind) in keybinds.iter_mut().enumerate() {
+                let mut key = bind.key.clone();
+                if ui
+                    .add(TextEdit::singleline(&mut key).desired_width(50.0))
+                    .changed()
+                {
+                    if let Some(key) = key_from_name(&key) {
+                        bind.key = key_name(key).to_string();
+                    }
+                }
+                ui.checkbox(&mut bind.shift, "Shift");
+
+                let kind = match bind.action {
+                    KeyAction::Twist { .. } => "Twist",
+                    KeyAction::Rotate { .. } => "Rotate",
+                    KeyAction::Layer(_) => "Layer",
+                };
+                let first_face = enum_iter::<Ray>().next().expect("there are rays").name();
+                ComboBox::from_id_source(("keybind_action", i))
+                    .selected_text(kind)
+                    .width(60.0)
+                    .show_ui(ui, |ui| {
+                        let twist = KeyAction::Twist {
+                            face: first_face.clone(),
+                            direction: 1,
+                            layers: vec![],
+                        };
+                        let rotate = KeyAction::Rotate {
+                            face: first_face.clone(),
+                            direction: 1,
+                        };
+                        for (name, action) in [
+                            ("Twist", twist),
+                            ("Rotate", rotate),
+                            ("Layer", KeyAction::Layer(1)),
+                        ] {
+                            if ui.selectable_label(kind == name, name).clicked() && kind != name {
+                                bind.action = action;
+                            }
+                        }
+                    });
+
+                match &mut bind.action {
+                    KeyAction::Twist {
+                        face, direction, ..
+                    }
+                    | KeyAction::Rotate { face, direction } => {
+                        ComboBox::from_id_source(("keybind_face", i))
+                            .selected_text(face.as_str())
+                            .width(40.0)
+                            .show_ui(ui, |ui| {
+                                for ray in enum_iter::<Ray>() {
+                                    ui.selectable_value(face, ray.name(), ray.name());
+                                }
+                            });
+                        ComboBox::from_id_source(("keybind_direction", i))
+                            .selected_text(if *direction > 0 { "⟳" } else { "⟲" })
+                            .width(30.0)
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(direction, 1, "⟳ Clockwise");
+                                ui.selectable_value(direction, -1, "⟲ Counterclockwise");
+                            });
+                    }
+                    KeyAction::Layer(layer) => {
+                        ui.add(DragValue::new(layer).clamp_range(1..=usize::MAX));
+                        ui.label("");
+                    }
+                }
+
+                if let KeyAction::Twist { layers, .. } = &mut bind.action {
+                    // the text is kept while editing so separators can be typed
+                    let id = ui.id().with(("keybind_layers", i));
+                    let mut text = ui.data_mut(|data| {
+                        data.get_temp::<String>(id).unwrap_or_else(|| {
+                            layers
+                                .iter()
+                                .map(usize::to_string)
+                                .collect::<Vec<_>>()
+                                .join(",")
+                        })
+                    });
+                    let response = ui.add(
+                        TextEdit::singleline(&mut text)
+                            .hint_text("layers")
+                            .desired_width(50.0),
+                    );
+                    if response.lost_focus() {
+                        if let Ok(new_layers) = text
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|s| !s.is_empty())
+                            .map(str::parse)
+                            .collect()
+                        {
+                            *layers = new_layers;
+                        }
+                        ui.data_mut(|data| data.remove::<String>(id));
+                    } else if response.has_focus() {
+                        ui.data_mut(|data| data.insert_temp(id, text));
+                    }
+                } else {
+                    ui.label("");
+                }
+
+                if ui.button("🗑").on_hover_text("Delete").clicked() {
+                    delete = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+    if let Some(i) = delete {
+        keybinds.remove(i);
+    }
+    if ui.button("Add keybind").clicked() {
+        keybinds.push(Keybind {
+            key: key_name(three_d::Key::Space).to_string(),
+            shift: false,
+            action: KeyAction::Layer(1),
+        });
+    }
+}
+
 // polyfill from egui 0.24.1
 fn selected_button(button: egui::Button, ui: &egui::Ui, selected: bool) -> egui::Button {
     if selected {
@@ -164,15 +530,521 @@ fn selected_button(button: egui::Button, ui: &egui::Ui, selected: bool) -> egui:
     }
 }
 
+/// State of the algorithm window
+#[derive(Default)]
+struct AlgorithmState {
+    open: bool,
+    text: String,
+    macro_name: String,
+    /// Whether transformations apply to the twist history instead of the text
+    transform_history: bool,
+    rotation: usize,
+}
+
+fn algorithm_window<Ray: ConcreteRaySystem>(
+    gui_context: &egui::Context,
+    state: &mut AlgorithmState,
+    prefs: &mut Preferences,
+    status_message: &mut Option<String>,
+    session: &mut Session<Ray>,
+    session_type: SessionType,
+) {
+    use egui::*;
+
+    let mut open = state.open;
+    Window::new("Algorithm")
+        .open(&mut open)
+        .default_width(300.0)
+        .show(gui_context, |ui| {
+            ui.add(
+                TextEdit::multiline(&mut state.text)
+                    .hint_text("[R, U] [F: R U R' U'] (R U)2")
+                    .desired_width(f32::INFINITY),
+            );
+            ui.horizontal(|ui| {
+                if ui.button("Apply").clicked() {
+                    *status_message = session
+                        .queue_algorithm(&state.text)
+                        .err()
+                        .map(|err| err.to_string());
+                }
+                if ui.button("Current moves").clicked() {
+                    state.text =
+                        notation::format_twists(&session.concrete_puzzle.puzzle, &session.twists);
+                }
+            });
+
+            ui.separator();
+            ui.heading("Transform");
+
+            let rotations = Symmetry::<Ray>::rotations();
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut state.transform_history, false, "Text");
+                ui.radio_value(&mut state.transform_history, true, "Twist history");
+            });
+            let mut transform = None;
+            ui.horizontal(|ui| {
+                ComboBox::from_id_source("transform_rotation")
+                    .selected_text(
+                        rotations
+                            .get(state.rotation)
+                            .map_or("?".to_string(), Symmetry::name),
+                    )
+                    .show_ui(ui, |ui| {
+                        for (j, rotation) in rotations.iter().enumerate() {
+                            ui.selectable_value(&mut state.rotation, j, rotation.name());
+                        }
+                    });
+                if ui.button("Rotate").clicked() {
+                    transform = rotations.get(state.rotation).cloned();
+                }
+                if ui.button("Mirror").clicked() {
+                    transform = Some(Symmetry::mirror());
+                }
+            });
+            let invert = ui.button("Invert").clicked();
+            if transform.is_some() || invert {
+                let apply = |twists: &[Twist<Ray>]| match &transform {
+                    Some(symmetry) => symmetry.apply_twists(twists),
+                    None => notation::invert(twists),
+                };
+                if state.transform_history {
+                    session.transform_twists(apply);
+                } else {
+                    let puzzle = &session.concrete_puzzle.puzzle;
+                    match notation::parse(puzzle, &state.text) {
+                        Ok(twists) => state.text = notation::format_twists(puzzle, &apply(&twists)),
+                        Err(err) => *status_message = Some(err.to_string()),
+                    }
+                }
+            }
+
+            ui.separator();
+            ui.heading("Macros");
+
+            let macros = prefs.macros.entry(session_type.to_string()).or_default();
+            let mut delete = None;
+            for (i, mac) in macros.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(&mac.name).on_hover_text(&mac.algorithm);
+                    ComboBox::from_id_source(("macro_orientation", i))
+                        .selected_text(
+                            rotations
+                                .get(mac.orientation)
+                                .map_or("?".to_string(), Symmetry::name),
+                        )
+                        .show_ui(ui, |ui| {
+                            for (j, rotation) in rotations.iter().enumerate() {
+                                ui.selectable_value(&mut mac.orientation, j, rotation.name());
+                            }
+                        });
+                    let mut key = mac.key.clone().unwrap_or_default();
+                    if ui
+                        .add(
+                            TextEdit::singleline(&mut key)
+                                .hint_text("key")
+                                .desired_width(40.0),
+                        )
+                        .changed()
+                    {
+                        mac.key = key_from_name(&key).map(|key| key_name(key).to_string());
+                    }
+                    if ui.button("Apply").clicked() {
+                        *status_message = session.queue_macro(mac).err().map(|err| err.to_string());
+                    }
+                    if ui.button("🗑").on_hover_text("Delete").clicked() {
+                        delete = Some(i);
+                    }
+                });
+            }
+            if let Some(i) = delete {
+                macros.remove(i);
+            }
+
+            ui.horizontal(|ui| {
+                ui.add(
+                    TextEdit::singleline(&mut state.macro_name)
+                        .hint_text("name")
+                        .desired_width(120.0),
+                );
+                if ui.button("Save as macro").clicked() {
+                    match notation::parse(&session.concrete_puzzle.puzzle, &state.text) {
+                        Ok(_) => macros.push(Macro {
+                            name: std::mem::take(&mut state.macro_name),
+                            algorithm: state.text.clone(),
+                            ..Default::default()
+                        }),
+                        Err(err) => *status_message = Some(err.to_string()),
+                    }
+                }
+            });
+        });
+    state.open = open;
+}
+
+fn piece_inspector<Ray: ConcreteRaySystem>(
+    gui_context: &egui::Context,
+    session: &mut Session<Ray>,
+) {
+    use egui::*;
+
+    let Some(piece_index) = session.inspected_piece else {
+        return;
+    };
+    // the inspector would give away the state of a blindfolded solve
+    if session.colors_hidden() {
+        return;
+    }
+    let puzzle = &session.concrete_puzzle.puzzle;
+    let piece = &puzzle.pieces[piece_index];
+    let format_grip = |grip: &[i8]| {
+        grip.iter()
+            .map(i8::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let mut open = true;
+    Window::new("Piece inspector")
+        .open(&mut open)
+        .resizable(false)
+        .show(gui_context, |ui| {
+            let this_type = &session.piece_types[piece_index];
+            let same_type = session
+                .piece_types
+                .iter()
+                .filter(|other| other == &this_type)
+                .count();
+            ui.label(format!(
+                "Type: [{}] ({same_type} pieces)",
+                format_grip(this_type)
+            ));
+            ui.label(if piece.is_solved() {
+                "Solved"
+            } else {
+                "Not solved"
+            });
+
+            ui.separator();
+            Grid::new("piece_inspector_grips")
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("Axis");
+                    ui.label("Solved grip");
+                    ui.label("Current grip");
+                    ui.end_row();
+                    for &axis in Ray::AXIS_HEADS {
+                        ui.label(axis.to_string());
+                        ui.label(format_grip(&piece.grip_on_axis_solved(axis)));
+                        ui.label(format_grip(&piece.grip_on_axis(axis)));
+                        ui.end_row();
+                    }
+                });
+
+            ui.separator();
+            CollapsingHeader::new("Orientation").show(ui, |ui| {
+                Grid::new("piece_inspector_orientation")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("Direction");
+                        ui.label("Side facing it");
+                        ui.end_row();
+                        for (ray, &facing) in &piece.orientation {
+                            ui.label(ray.to_string());
+                            ui.label(facing.to_string());
+                            ui.end_row();
+                        }
+                    });
+            });
+        });
+    if !open {
+        session.inspected_piece = None;
+    }
+}
+
+/// State of the grip selector window
+#[derive(Default)]
+struct GripSelectorState {
+    open: bool,
+    /// Whose key layers are shown when `viewport_keys` is on
+    viewport: usize,
+}
+
+fn grip_selector_window<Ray: ConcreteRaySystem>(
+    gui_context: &egui::Context,
+    state: &mut GripSelectorState,
+    layers: &mut LayerSelection,
+    prefs: &Preferences,
+    session: &Session<Ray>,
+) {
+    use egui::*;
+
+    let concrete_puzzle = &session.concrete_puzzle;
+    let mut open = state.open;
+    Window::new("Grips")
+        .open(&mut open)
+        .show(gui_context, |ui| {
+            let key_layers = if prefs.viewport_keys && concrete_puzzle.viewports.len() > 1 {
+                state.viewport = state.viewport.min(concrete_puzzle.viewports.len() - 1);
+                ComboBox::from_label("Viewport")
+                    .selected_text((state.viewport + 1).to_string())
+                    .show_ui(ui, |ui| {
+                        for i in 0..concrete_puzzle.viewports.len() {
+                            ui.selectable_value(&mut state.viewport, i, (i + 1).to_string());
+                        }
+                    });
+                &concrete_puzzle.viewports[state.viewport].key_layers
+            } else {
+                &concrete_puzzle.key_layers
+            };
+
+            ui.horizontal(|ui| {
+                if ui
+                    .button("Wide")
+                    .on_hover_text("Select every layer up to the deepest selected one")
+                    .clicked()
+                {
+                    layers.widen();
+                }
+                if ui.button("Clear").clicked() {
+                    layers.clear();
+                }
+            });
+            ui.label("Shift-click to select a range of layers.");
+            ui.label("Layers count from the twisted face, the rows below show their grips.");
+            ui.separator();
+
+            let selected = layers.selected();
+            // the selection is of layer numbers, not grips, so there is one row to pick from
+            Grid::new("grips").show(ui, |ui| {
+                ui.label("Layer");
+                for layer in 1..=key_layers[0].len() {
+                    let label = ui
+                        .selectable_label(selected.contains(&layer), layer.to_string())
+                        .on_hover_text(format!("Key {}", layer_key_name(layer)));
+                    if label.clicked() {
+                        if ui.input(|input| input.modifiers.shift) {
+                            layers.select_range(layer);
+                        } else {
+                            layers.toggle(layer);
+                        }
+                    }
+                }
+                ui.end_row();
+
+                for (i, grips) in key_layers.iter().enumerate() {
+                    let faces = Ray::AXIS_HEADS
+                        .iter()
+                        .map(|axis| axis.get_axis()[i].name())
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    ui.label(faces);
+                    for grip in grips {
+                        ui.label(grip.iter().map(i8::to_string).collect::<Vec<_>>().join(","));
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+    state.open = open;
+}
+
+/// State of the piece filter window
+#[derive(Default)]
+struct FilterState {
+    open: bool,
+    preset_name: String,
+}
+
+fn filter_window<Ray: ConcreteRaySystem>(
+    gui_context: &egui::Context,
+    state: &mut FilterState,
+    prefs: &mut Preferences,
+    session: &mut Session<Ray>,
+    session_type: SessionType,
+) {
+    use egui::*;
+
+    let format_grip = |grip: &[i8]| {
+        grip.iter()
+            .map(i8::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let mut open = state.open;
+    Window::new("Piece filter")
+        .open(&mut open)
+        .default_width(300.0)
+        .show(gui_context, |ui| {
+            let mut enabled = session.piece_filter.is_some();
+            ui.checkbox(&mut enabled, "Filter pieces");
+            if !enabled {
+                session.piece_filter = None;
+            } else if session.piece_filter.is_none() {
+                session.piece_filter = Some(Default::default());
+            }
+
+            if let Some(filter) = &mut session.piece_filter {
+                ComboBox::from_label("Other pieces")
+                    .selected_text(filter.style.to_string())
+                    .show_ui(ui, |ui| {
+                        for style in FilterStyle::ALL {
+                            ui.selectable_value(&mut filter.style, style, style.to_string());
+                        }
+                    });
+
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut filter.solved, SolvedFilter::Any, "Any");
+                    ui.radio_value(&mut filter.solved, SolvedFilter::Solved, "Solved");
+                    ui.radio_value(&mut filter.solved, SolvedFilter::Unsolved, "Unsolved");
+                });
+
+                CollapsingHeader::new("Piece types").show(ui, |ui| {
+                    let mut types: Vec<&Vec<i8>> = session.piece_types.iter().collect();
+                    types.sort();
+                    types.dedup();
+                    for piece_type in types {
+                        let count = session
+                            .piece_types
+                            .iter()
+                            .filter(|t| *t == piece_type)
+                            .count();
+                        let mut shown = filter.types.contains(piece_type);
+                        if ui
+                            .checkbox(
+                                &mut shown,
+                                format!("[{}] ({count})", format_grip(piece_type)),
+                            )
+                            .changed()
+                        {
+                            if shown {
+                                filter.types.push(piece_type.clone());
+                            } else {
+                                filter.types.retain(|t| t != piece_type);
+                            }
+                        }
+                    }
+                });
+
+                CollapsingHeader::new("Grips").show(ui, |ui| {
+                    let puzzle = &session.concrete_puzzle.puzzle;
+                    for (axis_index, axis) in Ray::AXIS_HEADS.iter().enumerate() {
+                        let current = filter
+                            .grips
+                            .iter()
+                            .find(|(i, _)| *i == axis_index)
+                            .map(|(_, grip)| *grip);
+                        let mut selected = current;
+                        ComboBox::from_label(axis.to_string())
+                            .selected_text(
+                                selected.map_or("Any".to_string(), |grip| format_grip(&grip)),
+                            )
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut selected, None, "Any");
+                                for grip in puzzle.grips_from_face(*axis) {
+                                    let text = format_grip(&grip);
+                                    ui.selectable_value(&mut selected, Some(grip), text);
+                                }
+                            });
+                        if selected != current {
+                            filter.grips.retain(|(i, _)| *i != axis_index);
+                            if let Some(grip) = selected {
+                                filter.grips.push((axis_index, grip));
+                            }
+                        }
+                    }
+                });
+            }
+
+            ui.separator();
+            ui.heading("Presets");
+
+            let presets = prefs.filters.entry(session_type.to_string()).or_default();
+            let mut delete = None;
+            for (i, preset) in presets.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    let active = session.piece_filter.as_ref() == Some(preset);
+                    if ui.selectable_label(active, &preset.name).clicked() {
+                        session.piece_filter = if active { None } else { Some(preset.clone()) };
+                    }
+                    if ui.button("🗑").on_hover_text("Delete").clicked() {
+                        delete = Some(i);
+                    }
+                });
+            }
+            if let Some(i) = delete {
+                presets.remove(i);
+            }
+
+            ui.horizontal(|ui| {
+                ui.add(
+                    TextEdit::singleline(&mut state.preset_name)
+                        .hint_text("name")
+                        .desired_width(120.0),
+                );
+                if let Some(filter) = &mut session.piece_filter {
+                    if ui.button("Save as preset").clicked() {
+                        filter.name = std::mem::take(&mut state.preset_name);
+                        presets.push(filter.clone());
+                    }
+                }
+            });
+        });
+    state.open = open;
+}
+
+/// Asks whether to restore the session autosaved by the last run.
+fn restore_prompt(
+    gui_context: &egui::Context,
+    autosave: &AutosaveFile,
+    response: &mut RenderLoopResponse,
+) {
+    use egui::*;
+
+    Window::new("Restore session")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+        .show(gui_context, |ui| {
+            ui.label("The last session had changes that were not saved.");
+            if let Some(path) = &autosave.save_path {
+                ui.label(format!("It was last saved to {}", path.display()));
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Restore").clicked() {
+                    response.restore = true;
+                }
+                if ui.button("Discard").clicked() {
+                    response.discard_autosave = true;
+                }
+            });
+        });
+}
+
 /// Mutable objects that have to persist through making a new session
 struct PersistentObjects {
     keys_down: HashSet<Key>,
-    keys_clicked: HashSet<Key>,
+    layers: LayerSelection,
+    /// Typed layer ranges like `2-5`
+    layer_text: String,
+    /// The mouse positions of a drag that twists, while it lasts
+    twist_drag: Option<Vec<LogicalPoint>>,
     status_message: Option<String>,
     window_size: (u32, u32),
+    /// None if the mouse is outside the window or over the GUI
+    mouse_position: Option<LogicalPoint>,
     gui: GUI,
     prefs: Preferences,
+    prefs_path: std::path::PathBuf,
     settings_open: bool,
+    algorithm: AlgorithmState,
+    filter: FilterState,
+    grip_selector: GripSelectorState,
+    autosave: Autosave,
+    /// The autosave of the last run, while asking whether to restore it
+    restore: Option<AutosaveFile>,
+    recent_files: Vec<std::path::PathBuf>,
 }
 
 impl PersistentObjects {
@@ -203,7 +1075,7 @@ impl PersistentObjects {
     }
 
     fn save_prefs(&mut self) {
-        match self.prefs.save() {
+        match self.prefs.save(&self.prefs_path) {
             Ok(()) => {
                 self.status_message = Some("Saved preferences".to_string());
             }
@@ -213,8 +1085,105 @@ impl PersistentObjects {
         }
     }
 
+    /// Stops autosaving until the session changes again.
+    fn mark_saved(&mut self, session: &SessionEnum) {
+        if let Err(err) = self.autosave.mark_saved(session) {
+            self.status_message = Some(format!("Error autosaving: {err}"));
+        }
+    }
+
+    /// Loads the session saved at the path, showing how it went.
+    fn load_session(&mut self, path: eyre::Result<std::path::PathBuf>) -> Option<SessionEnum> {
+        let load_result =
+            path.and_then(|path| SessionEnum::load(path, self.window_size, &self.prefs));
+        self.show_loaded(load_result)
+    }
+
+    /// Shows how loading a session went, and remembers where it was loaded from.
+    fn show_loaded(&mut self, load_result: eyre::Result<SessionEnum>) -> Option<SessionEnum> {
+        self.show_or(&load_result, |session| {
+            let suffix = if session.version() == VERSION {
+                "".to_string()
+            } else {
+                format!(" from earlier version {}", session.version())
+            };
+
+            format!(
+                "Loaded {}{suffix}",
+                session
+                    .save_path()
+                    .clone()
+                    .expect("the path should have been set")
+                    .display()
+            )
+            .to_string()
+        });
+        let session = load_result.ok()?;
+        if let Some(path) = session.save_path() {
+            self.add_recent_file(path);
+        }
+        self.mark_saved(&session);
+        Some(session)
+    }
+
+    /// Opens a file dropped on the window, which can be a log, preferences,
+    /// or a puzzle spec like `cube:5` to start that puzzle.
+    fn open_dropped_file(&mut self, path: std::path::PathBuf, response: &mut RenderLoopResponse) {
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                self.status_message = Some(format!("Error reading {}: {err}", path.display()));
+                return;
+            }
+        };
+        if log_format::looks_like_log(&bytes) {
+            let load_result = SessionEnum::from_bytes(&bytes, path, self.window_size, &self.prefs);
+            if let Some(session) = self.show_loaded(load_result) {
+                response.new_session = Some(session);
+            }
+        } else if let Ok(prefs) = Preferences::from_json(&bytes) {
+            self.prefs = prefs;
+            // the puzzle form might have changed
+            response.replace_concrete_puzzle = true;
+            self.status_message = Some(format!(
+                "Applied preferences from {}, save preferences to keep them",
+                path.display()
+            ));
+        } else if let Some(spec) = std::str::from_utf8(&bytes)
+            .ok()
+            .map(str::trim)
+            .filter(|text| !text.contains('\n'))
+        {
+            match spec.parse::<SessionType>() {
+                Ok(session_type) => {
+                    response.new_session =
+                        Some(session_type.make_session_enum(self.window_size, &self.prefs));
+                    self.status_message =
+                        Some(format!("Started {session_type} from {}", path.display()));
+                }
+                Err(err) => {
+                    self.status_message = Some(format!("Error opening {}: {err}", path.display()));
+                }
+            }
+        } else {
+            self.status_message = Some(format!(
+                "{} is not a log, puzzle or preferences file",
+                path.display()
+            ));
+        }
+    }
+
+    fn add_recent_file(&mut self, path: &std::path::Path) {
+        match autosave::add_recent_file(path) {
+            Ok(files) => self.recent_files = files,
+            Err(err) => {
+                self.status_message = Some(format!("Error saving recent files: {err}"));
+            }
+        }
+    }
+
     fn load_prefs(&mut self) {
-        match Preferences::load() {
+        match Preferences::load(&self.prefs_path) {
             Ok(prefs) => {
                 self.prefs = prefs;
             }
@@ -226,7 +1195,8 @@ impl PersistentObjects {
 }
 
 enum Save {
-    SavePath,
+    /// Picks a file to save to, in this format or else the session's current one
+    SavePath(Option<LogFormat>),
     SaveDefault,
 }
 
@@ -236,6 +1206,10 @@ struct RenderLoopResponse {
     new_session: Option<SessionEnum>,
     save: Option<Save>,
     load: bool,
+    /// A file to load without asking for one, like a recent file
+    load_path: Option<std::path::PathBuf>,
+    restore: bool,
+    discard_autosave: bool,
     save_prefs: bool,
     load_prefs: bool,
     replace_concrete_puzzle: bool,
@@ -247,6 +1221,7 @@ fn run_render_loop<Ray: ConcreteRaySystem + std::fmt::Display>(
     session: &mut Session<Ray>,
     persistent: &mut PersistentObjects,
     context: &Context,
+    session_type: SessionType,
 ) -> RenderLoopResponse {
     //println!("new frame");
 
@@ -270,6 +1245,14 @@ fn run_render_loop<Ray: ConcreteRaySystem + std::fmt::Display>(
         frame_input.device_pixel_ratio,
         |gui_context| {
             use egui::*;
+
+            // nothing else can be used until the prompt is answered,
+            // so the session does not change and overwrite the autosave being asked about
+            if let Some(autosave) = &persistent.restore {
+                restore_prompt(gui_context, autosave, &mut response);
+                return;
+            }
+
             #[allow(non_snake_case)]
             let COMMAND = Modifiers::COMMAND;
             #[allow(non_snake_case)]
@@ -282,6 +1265,25 @@ fn run_render_loop<Ray: ConcreteRaySystem + std::fmt::Display>(
                             response.load = true;
                             ui.close_menu();
                         }
+                        ui.menu_button("Open recent", |ui| {
+                            if persistent.recent_files.is_empty() {
+                                ui.label("No recent files");
+                            }
+                            for path in &persistent.recent_files {
+                                let name = path.file_name().map_or_else(
+                                    || path.display().to_string(),
+                                    |name| name.to_string_lossy().into_owned(),
+                                );
+                                if ui
+                                    .button(name)
+                                    .on_hover_text(path.display().to_string())
+                                    .clicked()
+                                {
+                                    response.load_path = Some(path.clone());
+                                    ui.close_menu();
+                                }
+                            }
+                        });
                         ui.separator();
                         if shortcut_button(ui, gui_context, "Save", COMMAND, Key::S).clicked() {
                             response.save = Some(Save::SaveDefault);
@@ -290,18 +1292,68 @@ fn run_render_loop<Ray: ConcreteRaySystem + std::fmt::Display>(
                         if shortcut_button(ui, gui_context, "Save as...", COMMAND_SHIFT, Key::S)
                             .clicked()
                         {
-                            response.save = Some(Save::SavePath);
+                            response.save = Some(Save::SavePath(None));
                             ui.close_menu();
                         }
+                        ui.menu_button("Save as format", |ui| {
+                            for format in LogFormat::ALL {
+                                if ui.button(format!("{format}...")).clicked() {
+                                    response.save = Some(Save::SavePath(Some(format)));
+                                    ui.close_menu();
+                                }
+                            }
+                        })
+                        .response
+                        .on_hover_text("Compact logs are much smaller for big puzzles");
                     });
+                    ui.menu_button("Camera", |ui| {
+                        if ui.button("Reset camera").clicked() {
+                            for facing in session.camera_facings.values_mut() {
+                                facing.reset();
+                            }
+                            ui.close_menu();
+                        }
+                        ui.menu_button("Look at", |ui| {
+                            for ray in enum_iter::<Ray>() {
+                                if ui.button(ray.name()).clicked() {
+                                    for (conjugate, facing) in session.camera_facings.iter_mut() {
+                                        facing.look_at_ray(ray, conjugate);
+                                    }
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                        let conjugate = session.concrete_puzzle.viewports[0].conjugate;
+                        let mut orthographic = session.camera_facings[conjugate].orthographic;
+                        if ui.checkbox(&mut orthographic, "Orthographic").changed() {
+                            for facing in session.camera_facings.values_mut() {
+                                facing.orthographic = orthographic;
+                            }
+                        }
+                        ui.separator();
+                        if ui.button("Remember camera for this puzzle").clicked() {
+                            persistent
+                                .prefs
+                                .cameras
+                                .insert(session_type.to_string(), session.camera_facing_list());
+                            response.save_prefs = true;
+                            ui.close_menu();
+                        }
+                        if ui.button("Forget remembered camera").clicked() {
+                            persistent.prefs.cameras.remove(&session_type.to_string());
+                            response.save_prefs = true;
+                            ui.close_menu();
+                        }
+                        ui.checkbox(&mut persistent.prefs.camera_in_log, "Save camera in logs");
+                    });
+
                     ui.menu_button("Puzzle", |ui| {
                         ui.menu_button("Cube", |ui| {
-                            for n in 2..=9 {
+                            for n in LAYER_RANGE {
                                 if ui.button(format!("{0} layers ({0}×{0}×{0})", n)).clicked() {
                                     response.new_session = Some(
                                         SessionType::Cube(CubePuzzle::Nnn(n)).make_session_enum(
                                             persistent.window_size,
-                                            context,
                                             &persistent.prefs,
                                         ),
                                     );
@@ -311,12 +1363,11 @@ fn run_render_loop<Ray: ConcreteRaySystem + std::fmt::Display>(
                         });
 
                         ui.menu_button("Octahedron", |ui| {
-                            for n in 2..=5 {
+                            for n in LAYER_RANGE {
                                 if ui.button(format!("{0} layers", n)).clicked() {
                                     response.new_session = Some(
                                         SessionType::Octa(OctaPuzzle::Fto(n)).make_session_enum(
                                             persistent.window_size,
-                                            context,
                                             &persistent.prefs,
                                         ),
                                     );
@@ -331,7 +1382,6 @@ fn run_render_loop<Ray: ConcreteRaySystem + std::fmt::Display>(
                                     SessionType::Dodeca(DodecaPuzzle::Pentultimate)
                                         .make_session_enum(
                                             persistent.window_size,
-                                            context,
                                             &persistent.prefs,
                                         ),
                                 );
@@ -341,7 +1391,6 @@ fn run_render_loop<Ray: ConcreteRaySystem + std::fmt::Display>(
                                 response.new_session = Some(
                                     SessionType::Dodeca(DodecaPuzzle::Megaminx).make_session_enum(
                                         persistent.window_size,
-                                        context,
                                         &persistent.prefs,
                                     ),
                                 );
@@ -355,7 +1404,6 @@ fn run_render_loop<Ray: ConcreteRaySystem + std::fmt::Display>(
                                     SessionType::RDodeca(RDodecaPuzzle::LittleChop)
                                         .make_session_enum(
                                             persistent.window_size,
-                                            context,
                                             &persistent.prefs,
                                         ),
                                 );
@@ -399,6 +1447,74 @@ fn run_render_loop<Ray: ConcreteRaySystem + std::fmt::Display>(
                                 persistent.status_message = None;
                             };
                         }
+                        ui.separator();
+                        ui.menu_button("Rotate", |ui| {
+                            let puzzle = &session.concrete_puzzle.puzzle;
+                            let rotations: Vec<_> = Ray::AXIS_HEADS
+                                .iter()
+                                .flat_map(|axis| axis.get_axis())
+                                .map(|ray| {
+                                    let rotation = ((ray, ray.clockwise()), puzzle.grips.clone());
+                                    (notation::format_twist(puzzle, &rotation), rotation.0)
+                                })
+                                .collect();
+                            for (name, turn) in rotations {
+                                if ui.button(name).clicked() {
+                                    session.rotate(turn, persistent.prefs.animation_length);
+                                }
+                            }
+                        });
+                        ui.menu_button("Put face on top", |ui| {
+                            let conjugate = session.concrete_puzzle.viewports[0].conjugate;
+                            let top = session.camera_facings[conjugate].top_ray::<Ray>(conjugate);
+                            for face in enum_iter::<Ray>() {
+                                let reachable = turns_moving(face, top).is_some();
+                                if ui
+                                    .add_enabled(reachable, Button::new(face.name()))
+                                    .clicked()
+                                {
+                                    persistent.status_message =
+                                        session.reorient(face, top).err().map(|e| e.to_string());
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                        if ui.button("Simplify moves").clicked() {
+                            session.simplify_twists();
+                            ui.close_menu();
+                        }
+                        ui.separator();
+                        match session.blindfold {
+                            None => {
+                                if ui.button("Start blindfolded memo").clicked() {
+                                    session.start_memo();
+                                    persistent.status_message = None;
+                                    ui.close_menu();
+                                }
+                            }
+                            Some(BlindfoldPhase::Memo { .. }) => {
+                                if ui.button("Done memorizing").clicked() {
+                                    persistent.status_message =
+                                        session.start_execution().err().map(|err| err.to_string());
+                                    ui.close_menu();
+                                }
+                            }
+                            Some(BlindfoldPhase::Execution { .. }) => {
+                                if ui.button("Done solving").clicked() {
+                                    persistent.status_message =
+                                        Some(match session.finish_blindfold() {
+                                            Ok(result) => result.to_string(),
+                                            Err(err) => err.to_string(),
+                                        });
+                                    ui.close_menu();
+                                }
+                            }
+                        }
+                        if session.blindfold.is_some() && ui.button("Cancel blindfolded").clicked()
+                        {
+                            session.blindfold = None;
+                            ui.close_menu();
+                        }
                     });
 
                     if ui
@@ -411,38 +1527,99 @@ fn run_render_loop<Ray: ConcreteRaySystem + std::fmt::Display>(
                     {
                         persistent.settings_open = !persistent.settings_open;
                     }
+
+                    if ui
+                        .add(selected_button(
+                            Button::new("Algorithm"),
+                            ui,
+                            persistent.algorithm.open,
+                        ))
+                        .clicked()
+                    {
+                        persistent.algorithm.open = !persistent.algorithm.open;
+                    }
+
+                    if ui
+                        .add(selected_button(
+                            Button::new("Filter"),
+                            ui,
+                            persistent.filter.open,
+                        ))
+                        .clicked()
+                    {
+                        persistent.filter.open = !persistent.filter.open;
+                    }
+
+                    if ui
+                        .add(selected_button(
+                            Button::new("Grips"),
+                            ui,
+                            persistent.grip_selector.open,
+                        ))
+                        .clicked()
+                    {
+                        persistent.grip_selector.open = !persistent.grip_selector.open;
+                    }
                 });
             });
 
             TopBottomPanel::bottom("status_bar").show(gui_context, |ui| {
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let layer_edit = ui.add(
+                        egui::TextEdit::singleline(&mut persistent.layer_text)
+                            .hint_text("Layers")
+                            .desired_width(60.0),
+                    );
+                    if layer_edit.lost_focus() {
+                        match parse_layer_list(&persistent.layer_text) {
+                            Ok(layers) => persistent.layers.set_clicked(layers),
+                            Err(err) => persistent.status_message = Some(err.to_string()),
+                        }
+                    }
                     // this detects key presses one frame late
-                    for i in (0..NUMBER_KEYS.len()).rev() {
-                        let num_key = NUMBER_KEYS[i];
-                        let status = if persistent.keys_clicked.contains(&num_key) {
+                    let layer_count = session.concrete_puzzle.key_layers[0].len();
+                    for layer in (1..=layer_count).rev() {
+                        let status = if persistent.layers.is_clicked(layer) {
                             KeyLabelStatus::Clicked
-                        } else if persistent.keys_down.contains(&num_key) {
+                        } else if persistent.layers.is_held(layer) {
                             KeyLabelStatus::Pressed
                         } else {
                             KeyLabelStatus::Unpressed
                         };
-                        #[allow(clippy::collapsible_if)]
-                        if session.concrete_puzzle.key_layers[0].contains_key(&num_key) {
-                            if ui.add(KeyLabel::new(status, (i + 1).to_string())).clicked() {
-                                if persistent.keys_clicked.contains(&num_key) {
-                                    persistent.keys_clicked.remove(&num_key);
-                                } else {
-                                    persistent.keys_clicked.insert(num_key);
-                                }
-                            };
+                        if ui
+                            .add(KeyLabel::new(status, layer_key_name(layer)))
+                            .clicked()
+                        {
+                            persistent.layers.toggle(layer);
                         }
                     }
                     ui.separator();
+                    ui.label(session.move_count().to_string());
+                    ui.separator();
+                    match session.blindfold {
+                        Some(BlindfoldPhase::Memo { memo }) => {
+                            ui.label(format!("Memo {:.1}s", memo / 1000.0));
+                            ui.separator();
+                        }
+                        Some(BlindfoldPhase::Execution {
+                            memo, execution, ..
+                        }) => {
+                            ui.label(format!(
+                                "Memo {:.1}s  Execution {:.1}s",
+                                memo / 1000.0,
+                                execution / 1000.0
+                            ));
+                            ui.separator();
+                        }
+                        None => (),
+                    }
 
                     ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
                         if let Some(message) = &persistent.status_message {
                             ui.label(message.as_str());
-                        } else if session.concrete_puzzle.puzzle.is_solved() {
+                        } else if !session.colors_hidden()
+                            && session.concrete_puzzle.puzzle.is_solved()
+                        {
                             // if the message is None, we display one of the weaker messages
                             ui.label("Solved!");
                         }
@@ -471,11 +1648,75 @@ fn run_render_loop<Ray: ConcreteRaySystem + std::fmt::Display>(
                             );
                         });
 
+                        ui.collapsing("Appearance", |ui| {
+                            let visuals = &mut persistent.prefs.visuals;
+                            ui.horizontal(|ui| {
+                                let mut background = visuals.background.as_array();
+                                ui.color_edit_button_srgb(&mut background);
+                                visuals.background = background.into();
+                                ui.label("Background");
+                            });
+                            ui.checkbox(&mut visuals.lighting, "Lighting");
+
+                            // these change the sticker meshes
+                            let mut remesh = false;
+                            remesh |= ui
+                                .add(Slider::new(&mut visuals.sticker_gap, 0.0..=0.5).text("Gaps"))
+                                .changed();
+                            remesh |= ui
+                                .add(
+                                    Slider::new(&mut visuals.outline_width, 0.0..=0.5)
+                                        .text("Outlines"),
+                                )
+                                .changed();
+                            ui.horizontal(|ui| {
+                                let mut outline_color = visuals.outline_color.as_array();
+                                remesh |= ui.color_edit_button_srgb(&mut outline_color).changed();
+                                visuals.outline_color = outline_color.into();
+                                ui.label("Outline and plastic color");
+                            });
+                            remesh |= ui
+                                .checkbox(&mut visuals.internal_faces, "Plastic behind stickers")
+                                .changed();
+
+                            let concrete = &mut persistent.prefs.concrete;
+                            remesh |= ui
+                                .add(
+                                    Slider::new(&mut concrete.cube_super_start, 0.0..=0.95)
+                                        .text("Cube super-stickers"),
+                                )
+                                .changed();
+                            remesh |= ui
+                                .add(
+                                    Slider::new(&mut concrete.little_chop_super_start, 0.0..=0.95)
+                                        .text("Little chop super-stickers"),
+                                )
+                                .changed();
+                            remesh |= ui
+                                .checkbox(&mut concrete.sparse, "Only make pieces with stickers")
+                                .on_hover_text("Saves memory and time on big puzzles")
+                                .changed();
+                            if remesh {
+                                response.replace_concrete_puzzle = true;
+                            }
+                        });
+
                         ui.collapsing("Controls", |ui| {
                             ui.checkbox(
                                 &mut persistent.prefs.viewport_keys,
                                 "Per-viewport layer keys",
                             );
+                            ui.checkbox(
+                                &mut persistent.prefs.hover_highlight,
+                                "Highlight layers under the mouse",
+                            );
+                            ui.checkbox(
+                                &mut persistent.prefs.drag_to_twist,
+                                "Drag stickers to twist",
+                            )
+                            .on_hover_text(
+                                "Hold ctrl or drag from the background to orbit the camera",
+                            );
 
                             ui.horizontal(|ui| {
                                 reset_button_small(
@@ -493,6 +1734,15 @@ fn run_render_loop<Ray: ConcreteRaySystem + std::fmt::Display>(
                             });
                         });
 
+                        ui.collapsing("Keybinds", |ui| {
+                            keybind_editor::<Ray>(
+                                ui,
+                                &mut persistent.prefs,
+                                session_type.family(),
+                                &mut persistent.status_message,
+                            );
+                        });
+
                         ui.collapsing("Puzzle form", |ui| {
                             if ui
                                 .checkbox(
@@ -521,14 +1771,65 @@ fn run_render_loop<Ray: ConcreteRaySystem + std::fmt::Display>(
                     });
                 });
             }
+
+            if persistent.algorithm.open {
+                algorithm_window(
+                    gui_context,
+                    &mut persistent.algorithm,
+                    &mut persistent.prefs,
+                    &mut persistent.status_message,
+                    session,
+                    session_type,
+                );
+            }
+
+            if persistent.grip_selector.open {
+                grip_selector_window(
+                    gui_context,
+                    &mut persistent.grip_selector,
+                    &mut persistent.layers,
+                    &persistent.prefs,
+                    session,
+                );
+            }
+
+            if persistent.filter.open {
+                filter_window(
+                    gui_context,
+                    &mut persistent.filter,
+                    &mut persistent.prefs,
+                    session,
+                    session_type,
+                );
+            }
+
+            piece_inspector(gui_context, session);
         },
     );
 
+    if persistent.restore.is_some() {
+        // the restore prompt takes all the input until it is answered
+        frame_input.events.clear();
+    }
+
+    for event in &frame_input.events {
+        match *event {
+            Event::MouseMotion {
+                position, handled, ..
+            } => {
+                persistent.mouse_position = (!handled).then_some(position);
+            }
+            Event::MouseLeave => persistent.mouse_position = None,
+            _ => (),
+        }
+    }
+
     for event in &frame_input.events {
         match *event {
             Event::MousePress {
                 button,
                 position,
+                modifiers,
                 handled,
                 ..
             } if !handled => {
@@ -538,6 +1839,13 @@ fn run_render_loop<Ray: ConcreteRaySystem + std::fmt::Display>(
                     session.mouse_press_location =
                         Some((viewport_clicked.conjugate, Some((position, button))));
                 }
+                // with ctrl or from the background, dragging orbits the camera instead
+                let orbit = modifiers.ctrl || modifiers.command;
+                persistent.twist_drag = (persistent.prefs.drag_to_twist
+                    && !orbit
+                    && button != MouseButton::Middle
+                    && sticker_at_pixel(session, position).is_some())
+                .then(|| vec![position]);
             }
             Event::MouseMotion {
                 button: Some(MouseButton::Left | MouseButton::Right),
@@ -546,6 +1854,10 @@ fn run_render_loop<Ray: ConcreteRaySystem + std::fmt::Display>(
                 handled,
                 ..
             } if !handled => {
+                if let Some(positions) = &mut persistent.twist_drag {
+                    positions.push(position);
+                    continue;
+                }
                 match session.mouse_press_location {
                     Some((conjugate, Some((press_position, _)))) => {
                         let distance_moved = f32::hypot(
@@ -570,93 +1882,76 @@ fn run_render_loop<Ray: ConcreteRaySystem + std::fmt::Display>(
                     }
                 }
             }
+            Event::MouseWheel {
+                delta: (_, dy),
+                position,
+                handled,
+                ..
+            } if !handled => {
+                if let Some(viewport) = get_viewport_from_pixel(&session.concrete_puzzle, position)
+                {
+                    session.camera_facings[viewport.conjugate].zoom_by(dy);
+                }
+            }
             Event::MouseRelease {
                 button,
                 position,
                 handled,
                 ..
             } if !handled => {
-                if let Some(viewport_clicked) =
-                    get_viewport_from_pixel(&session.concrete_puzzle, position)
-                {
-                    let camera = viewport_clicked
-                        .make_camera(&session.camera_facings[viewport_clicked.conjugate]);
-
-                    let sticker_m = viewport_clicked.ray_intersect(
-                        camera.position_at_pixel(position),
-                        camera.view_direction_at_pixel(position),
-                    );
-
-                    if let Some(sticker) = sticker_m {
-                        if button == MouseButton::Middle {
-                            // needs fixing!
-                            /*persistent.status_message = Some(format!(
-                                "position: {}, face: {}, color: {}, piece: {}",
-                                session
-                                    .concrete_puzzle
-                                    .puzzle
-                                    .index_to_solved_piece(sticker.piece_ind),
-                                sticker.face,
-                                sticker.color,
-                                session.concrete_puzzle.puzzle.pieces[session
-                                    .concrete_puzzle
-                                    .puzzle
-                                    .permutation()[sticker.piece_ind]]
-                            ));*/
-                        } else if let Some((_conjugate, Some((_, press_button)))) =
-                            session.mouse_press_location
-                        {
-                            persistent.status_message = None;
-
-                            if press_button == button {
-                                let turn_direction =
-                                    match button {
-                                        three_d::MouseButton::Left => -1,
-                                        three_d::MouseButton::Right => 1,
-                                        _ => 0, // should never happen
-                                    } * Ray::order_conjugate(viewport_clicked.conjugate);
-
-                                let turn_face = sticker.face;
-                                let axis_index = turn_face
-                                    .get_axis()
-                                    .iter()
-                                    .position(|&r| r == turn_face)
-                                    .expect("rays are always in their axes");
-                                let opposite_axis = (-1i8).pow(axis_index as u32);
-                                let turn = (turn_face, opposite_axis * turn_direction);
-
-                                let keys = persistent.keys_down.union(&persistent.keys_clicked);
-
-                                // if an invalid key is pressed on a puzzle while in viewport key mode,
-                                // there should be no turn instead of doing the default turn
-                                let default_mode = !keys.clone().any(|key| {
-                                    session.concrete_puzzle.key_layers[0].contains_key(key)
-                                });
-
-                                let grips = if default_mode {
-                                    // rustfmt? hewwo?
-                                    if let Some(layer) =
-                                        viewport_clicked.key_layers[axis_index].get(&NUMBER_KEYS[0])
-                                    {
-                                        vec![layer.clone()]
-                                    } else {
-                                        vec![]
-                                    }
-                                } else {
-                                    let key_layers = if persistent.prefs.viewport_keys {
-                                        &viewport_clicked.key_layers
-                                    } else {
-                                        &session.concrete_puzzle.key_layers
-                                    };
-                                    let grips_: Vec<_> = keys
-                                        .filter_map(|key| key_layers[axis_index].get(key))
-                                        .collect();
-
-                                    grips_.into_iter().cloned().collect()
-                                };
-
-                                session.twist(turn, grips, persistent.prefs.animation_length);
+                if let Some(mut positions) = persistent.twist_drag.take() {
+                    positions.push(position);
+                    let distance_moved =
+                        f32::hypot(position.x - positions[0].x, position.y - positions[0].y);
+                    // short drags are clicks
+                    if distance_moved > TURN_DISTANCE_THRESHOLD {
+                        persistent.status_message = None;
+                        if let Some((turn, grips)) = drag_twist(session, &positions) {
+                            session.twist(turn, grips, persistent.prefs.animation_length);
+                        }
+                        session.mouse_press_location = None;
+                        continue;
+                    }
+                }
+                if let Some((viewport_clicked, sticker)) = sticker_at_pixel(session, position) {
+                    if button == MouseButton::Middle {
+                        let puzzle = &session.concrete_puzzle.puzzle;
+                        let piece = puzzle.piece_index_by_ind(sticker.piece_ind);
+                        // clicking the inspected piece again closes the inspector
+                        session.inspected_piece = if session.inspected_piece == Some(piece) {
+                            None
+                        } else {
+                            Some(piece)
+                        };
+                    } else if let Some((_conjugate, Some((_, press_button)))) =
+                        session.mouse_press_location
+                    {
+                        persistent.status_message = None;
+
+                        if press_button == button {
+                            let turn_direction =
+                                match button {
+                                    three_d::MouseButton::Left => -1,
+                                    three_d::MouseButton::Right => 1,
+                                    _ => 0, // should never happen
+                                } * Ray::order_conjugate(viewport_clicked.conjugate);
+
+                            let turn_face = sticker.face;
+                            let turn = (turn_face, turn_face.clockwise() * turn_direction);
+
+                            let grips = selected_grips(
+                                &session.concrete_puzzle,
+                                viewport_clicked,
+                                &persistent.layers.selected(),
+                                persistent.prefs.viewport_keys,
+                                turn_face,
+                            );
+                            // a selected layer the puzzle does not have
+                            if grips.is_empty() {
+                                continue;
                             }
+
+                            session.twist(turn, grips, persistent.prefs.animation_length);
                         }
                     }
                 }
@@ -672,6 +1967,21 @@ fn run_render_loop<Ray: ConcreteRaySystem + std::fmt::Display>(
                 persistent.keys_down.insert(kind);
 
                 let ctrl = modifiers.ctrl || modifiers.command;
+                let keybinds = persistent.prefs.keybinds(session_type.family());
+                let puzzle_macros = persistent.prefs.macros.get(&session_type.to_string());
+                let key_macro = puzzle_macros.and_then(|macros| {
+                    macros
+                        .iter()
+                        .find(|mac| mac.key.as_deref() == Some(key_name(kind)))
+                });
+                // digits select layers, unless they are bound to something
+                let bound = keybinds
+                    .iter()
+                    .any(|bind| bind.matches(kind, modifiers.shift))
+                    || key_macro.is_some();
+                if !ctrl && !bound && persistent.layers.press(kind, &modifiers) {
+                    continue;
+                }
 
                 persistent.show_err(match (kind, modifiers.shift, ctrl) {
                     (Key::Z, false, true) => session.undo(persistent.prefs.animation_length),
@@ -688,27 +1998,57 @@ fn run_render_loop<Ray: ConcreteRaySystem + std::fmt::Display>(
                         Ok(())
                     }
                     (Key::S, true, true) => {
-                        response.save = Some(Save::SavePath);
+                        response.save = Some(Save::SavePath(None));
                         Ok(())
                     }
+                    (_, shift, false) => do_keybind(
+                        session,
+                        &keybinds,
+                        (kind, shift),
+                        &persistent.keys_down,
+                        &persistent.layers.selected(),
+                        &persistent.prefs,
+                    )
+                    .unwrap_or_else(|| key_macro.map_or(Ok(()), |mac| session.queue_macro(mac))),
                     _ => Ok(()),
                 });
             }
             Event::KeyRelease { kind, handled, .. } if !handled => {
                 persistent.keys_down.remove(&kind);
+                persistent.layers.release(kind);
             }
             _ => (),
         }
     }
 
+    session.advance_queue(persistent.prefs.animation_length);
+    session.advance_blindfold(frame_input.elapsed_time);
+
+    // not while the camera is orbiting
+    let orbiting = matches!(session.mouse_press_location, Some((_, None)));
+    session.hovered_layers = persistent
+        .mouse_position
+        .filter(|_| persistent.prefs.hover_highlight && !orbiting)
+        .and_then(|position| {
+            let (viewport, sticker) = sticker_at_pixel(session, position)?;
+            let grips = selected_grips(
+                &session.concrete_puzzle,
+                viewport,
+                &persistent.layers.selected(),
+                persistent.prefs.viewport_keys,
+                sticker.face,
+            );
+            Some((sticker.face, grips))
+        });
+
     // these should go above the events loop, otherwise the first turns will lag
     // maybe not
     render_puzzle(
         &mut frame_input.screen(),
+        context,
         frame_input.elapsed_time,
-        &mut session.concrete_puzzle,
+        session,
         &persistent.prefs,
-        &session.camera_facings,
     );
 
     frame_input.screen().write(|| persistent.gui.render());
@@ -717,95 +2057,200 @@ fn run_render_loop<Ray: ConcreteRaySystem + std::fmt::Display>(
 }
 
 fn main() {
-    let window = Window::new(WindowSettings {
-        title: "Laminated".to_string(),
-        max_size: Some((1280, 720)),
-        ..Default::default()
-    })
-    .expect("should create window");
+    let args = match args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{err}\n\n{}", args::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{}", args::USAGE);
+        return;
+    }
+
+    let window = window::Window::new("Laminated", (1280, 720)).expect("should create window");
 
     let context = window.gl();
     context.set_cull(Cull::Back);
 
     let mut persistent = PersistentObjects {
         keys_down: HashSet::new(),
-        keys_clicked: HashSet::new(),
+        layers: LayerSelection::default(),
+        layer_text: String::new(),
+        twist_drag: None,
         status_message: None,
         window_size: window.size(),
+        mouse_position: None,
         gui: GUI::new(&context),
         prefs: Default::default(),
+        prefs_path: args.prefs.clone().unwrap_or_else(|| PREFS_PATH.into()),
         settings_open: false,
+        algorithm: Default::default(),
+        filter: Default::default(),
+        grip_selector: Default::default(),
+        autosave: Default::default(),
+        restore: None,
+        recent_files: autosave::recent_files(),
     };
 
     persistent.load_prefs();
 
-    let mut session = SessionType::Cube(CubePuzzle::Nnn(3)).make_session_enum(
-        persistent.window_size,
-        &context,
-        &persistent.prefs,
-    );
+    // a session picked on the command line is not replaced by the last one
+    if !args.picks_session() {
+        match AutosaveFile::load() {
+            Ok(restore) => persistent.restore = restore,
+            Err(err) => persistent.status_message = Some(format!("Error reading autosave: {err}")),
+        }
+    }
 
-    window.render_loop(move |mut frame_input| {
-        let response = match &mut session {
-            SessionEnum::Cube(_, ref mut session) => {
-                run_render_loop(&mut frame_input, session, &mut persistent, &context)
-            }
-            SessionEnum::Octa(_, ref mut session) => {
-                run_render_loop(&mut frame_input, session, &mut persistent, &context)
-            }
-            SessionEnum::Dodeca(_, ref mut session) => {
-                run_render_loop(&mut frame_input, session, &mut persistent, &context)
-            }
-            SessionEnum::RDodeca(_, ref mut session) => {
-                run_render_loop(&mut frame_input, session, &mut persistent, &context)
-            }
+    let mut session = args
+        .puzzle
+        .unwrap_or(SessionType::Cube(CubePuzzle::Nnn(3)))
+        .make_session_enum(persistent.window_size, &persistent.prefs);
+    if let Some(seed) = args.seed {
+        session.scramble_seeded(seed);
+    }
+    persistent.mark_saved(&session);
+    if let Some(log) = args.log {
+        if let Some(loaded) = persistent.load_session(Ok(log)) {
+            session = loaded;
+        }
+    }
+
+    window.render_loop(move |mut frame_input, dropped_files, closing| {
+        let session_type = session.get_type();
+        let mut response = match &mut session {
+            SessionEnum::Cube(_, ref mut session) => run_render_loop(
+                &mut frame_input,
+                session,
+                &mut persistent,
+                &context,
+                session_type,
+            ),
+            SessionEnum::Octa(_, ref mut session) => run_render_loop(
+                &mut frame_input,
+                session,
+                &mut persistent,
+                &context,
+                session_type,
+            ),
+            SessionEnum::Dodeca(_, ref mut session) => run_render_loop(
+                &mut frame_input,
+                session,
+                &mut persistent,
+                &context,
+                session_type,
+            ),
+            SessionEnum::RDodeca(_, ref mut session) => run_render_loop(
+                &mut frame_input,
+                session,
+                &mut persistent,
+                &context,
+                session_type,
+            ),
         };
 
+        // like everything else, dropped files are ignored until the restore prompt is answered
+        if persistent.restore.is_none() {
+            for path in dropped_files {
+                persistent.open_dropped_file(path, &mut response);
+            }
+        }
+
         if let Some(new_session) = response.new_session {
             session = new_session;
+            persistent.mark_saved(&session);
         }
 
         if let Some(save_type) = response.save {
-            let save_path: Result<std::path::PathBuf, eyre::Report> =
+            // a file keeps its format unless another one is picked
+            let current_format = session.save_format().unwrap_or(persistent.prefs.log_format);
+            let save_path: Result<(std::path::PathBuf, LogFormat), eyre::Report> =
                 match (save_type, session.save_path()) {
-                    (Save::SaveDefault, Some(save_path)) => Ok(save_path.clone()),
-                    _ => file_dialog()
+                    (Save::SaveDefault, Some(save_path)) => Ok((save_path.clone(), current_format)),
+                    (Save::SaveDefault, None) | (Save::SavePath(None), _) => file_dialog()
                         .save_file()
+                        .map(|path| (path, current_format))
                         .ok_or_else(|| eyre!("No file picked")),
+                    (Save::SavePath(Some(format)), _) => {
+                        persistent.prefs.log_format = format;
+                        file_dialog()
+                            .save_file()
+                            .map(|path| (path, format))
+                            .ok_or_else(|| eyre!("No file picked"))
+                    }
                 };
 
-            persistent.show_or(&save_path.and_then(|path| session.save_as(&path)), |path| {
+            let save_result = save_path.and_then(|(path, format)| {
+                session.save_as(&path, persistent.prefs.camera_in_log, format)
+            });
+            persistent.show_or(&save_result, |path| {
                 format!("Saved to {}", path.display()).to_string()
             });
+            if let Ok(path) = save_result {
+                persistent.add_recent_file(&path);
+                persistent.mark_saved(&session);
+            }
         }
 
-        if response.load {
-            let load_path = file_dialog()
-                .pick_file()
-                .ok_or_else(|| eyre!("No file picked"));
-
-            let load_result = load_path.and_then(|path| {
-                SessionEnum::load(path, persistent.window_size, &context, &persistent.prefs)
-            });
-            persistent.show_or(&load_result, |session| {
-                let suffix = if session.version() == VERSION {
-                    "".to_string()
-                } else {
-                    format!(" from earlier version {}", session.version())
-                };
+        let load_path = match response.load_path {
+            Some(path) => Some(Ok(path)),
+            None => response.load.then(|| {
+                file_dialog()
+                    .pick_file()
+                    .ok_or_else(|| eyre!("No file picked"))
+            }),
+        };
+        if let Some(new_session) = load_path.and_then(|path| persistent.load_session(path)) {
+            session = new_session;
+        }
 
-                format!(
-                    "Loaded {}{suffix}",
-                    session
-                        .save_path()
-                        .clone()
-                        .expect("the path should have been set")
-                        .display()
+        if response.restore {
+            let autosave = persistent
+                .restore
+                .take()
+                .expect("restore is only asked for with an autosave");
+            let restored = autosave.log().and_then(|log| {
+                SessionEnum::from_log(
+                    log,
+                    persistent.window_size,
+                    autosave.save_path,
+                    &persistent.prefs,
                 )
-                .to_string()
+                .map(|mut session| {
+                    session.set_save_format(autosave.save_format);
+                    session
+                })
             });
-            if let Ok(new_session) = load_result {
-                session = new_session;
+            persistent.show_or(&restored, |_| "Restored the last session".to_string());
+            if let Ok(restored) = restored {
+                session = restored;
+                // it is only in the autosave, so keep autosaving it
+                persistent.autosave.mark_unsaved();
+            }
+        }
+
+        if response.discard_autosave {
+            persistent.restore = None;
+            persistent.show_err(AutosaveFile::discard());
+        }
+
+        // the autosave is kept until the user decides whether to restore it
+        if persistent.restore.is_none() {
+            let result = if closing {
+                // the changes since the last check would be lost otherwise
+                persistent.autosave.save(&session)
+            } else {
+                persistent
+                    .autosave
+                    .update(frame_input.accumulated_time, &session)
+            };
+            if let Err(err) = result {
+                if closing {
+                    eprintln!("Error autosaving: {err}");
+                }
+                persistent.status_message = Some(format!("Error autosaving: {err}"));
             }
         }
 
@@ -818,11 +2263,11 @@ fn main() {
         }
 
         if response.replace_concrete_puzzle {
-            session.replace_concrete_puzzle_from(session.get_type().make_session_enum(
-                persistent.window_size,
-                &context,
-                &persistent.prefs,
-            ))
+            session.replace_concrete_puzzle_from(
+                session
+                    .get_type()
+                    .make_session_enum(persistent.window_size, &persistent.prefs),
+            )
         }
 
         FrameOutput::default()
@@ -1,16 +1,22 @@
+use crate::keybinds::{default_keybinds, Keybind};
+use crate::log_format::LogFormat;
 use crate::puzzle::cube::CubeRay;
 use crate::puzzle::dodeca::DodecaRay;
+use crate::puzzle::filter::PieceFilter;
 use crate::puzzle::octa::OctaRay;
 use crate::puzzle::r_dodeca::RDodecaRay;
-use crate::render::common::ConcreteRaySystem;
+use crate::render::common::{CameraFacing, ConcreteRaySystem};
 use crate::util::color;
 use crate::util::color::Color;
 use enum_map::enum_map;
 use enum_map::EnumMap;
+use eyre::eyre;
 use serde::Deserialize;
 use serde::Serialize;
+use std::collections::HashMap;
 
-const PREFS_PATH: &'static str = "./preferences.json";
+/// Where preferences are kept unless the command line says otherwise.
+pub const PREFS_PATH: &'static str = "./preferences.json";
 
 #[derive(Serialize, Deserialize)]
 pub struct ColorPreferences {
@@ -39,21 +45,90 @@ impl Default for ColorPreferences {
     }
 }
 
+fn default_cube_super_start() -> f32 {
+    0.75
+}
+
+fn default_little_chop_super_start() -> f32 {
+    0.8
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct ConcretePuzzlePreferences {
     pub octa_extend: bool,
+    /// Where the colors of a super-sticker on a cube's x-centers split, from -1 to 1
+    #[serde(default = "default_cube_super_start")]
+    pub cube_super_start: f32,
+    /// Where the colors of a super-sticker on the little chop split, from 0 to 1
+    #[serde(default = "default_little_chop_super_start")]
+    pub little_chop_super_start: f32,
+    /// Only make the pieces that have stickers, for big puzzles
+    #[serde(default)]
+    pub sparse: bool,
 }
 
 impl Default for ConcretePuzzlePreferences {
     fn default() -> Self {
-        Self { octa_extend: true }
+        Self {
+            octa_extend: true,
+            cube_super_start: default_cube_super_start(),
+            little_chop_super_start: default_little_chop_super_start(),
+            sparse: false,
+        }
+    }
+}
+
+/// How stickers and the background look.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct VisualPreferences {
+    pub background: Color,
+    /// How much of each sticker is left as a gap, from 0 to 1
+    pub sticker_gap: f32,
+    /// How much of each sticker is taken by its outline, from 0 to 1
+    pub outline_width: f32,
+    /// The color of outlines and of the plastic behind stickers
+    pub outline_color: Color,
+    /// Draw plastic behind the stickers, which shows through the gaps
+    pub internal_faces: bool,
+    /// Shade stickers by how much they face the light
+    pub lighting: bool,
+}
+
+impl Default for VisualPreferences {
+    fn default() -> Self {
+        Self {
+            background: Color::hex(0xcccccc),
+            sticker_gap: 0.0,
+            outline_width: 0.0,
+            outline_color: Color::hex(0x101010),
+            internal_faces: false,
+            lighting: false,
+        }
     }
 }
 
+/// A saved algorithm.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Macro {
+    pub name: String,
+    pub algorithm: String,
+    /// Name of the key that applies the macro, from `util::key_names`.
+    #[serde(default)]
+    pub key: Option<String>,
+    /// Index of the rotation in `Symmetry::rotations` the macro is applied with.
+    #[serde(default)]
+    pub orientation: usize,
+}
+
 fn default_animation_length() -> f32 {
     150.0
 }
 
+fn default_hover_highlight() -> bool {
+    true
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Preferences {
     #[serde(default)]
@@ -62,8 +137,36 @@ pub struct Preferences {
     pub viewport_keys: bool,
     #[serde(default)]
     pub concrete: ConcretePuzzlePreferences,
+    #[serde(default)]
+    pub visuals: VisualPreferences,
     #[serde(default = "default_animation_length")]
     pub animation_length: f32,
+    /// Highlight the layers that clicking would turn
+    #[serde(default = "default_hover_highlight")]
+    pub hover_highlight: bool,
+    /// Dragging across stickers twists, and the camera orbits with ctrl or from the background
+    #[serde(default)]
+    pub drag_to_twist: bool,
+    /// Remembered camera facings for each puzzle type, keyed by the puzzle type's spec.
+    #[serde(default)]
+    pub cameras: HashMap<String, Vec<CameraFacing>>,
+    /// Save the camera facings in log files
+    #[serde(default)]
+    pub camera_in_log: bool,
+    /// The format last picked in Save as, used the first time a new session is saved.
+    /// Saving a loaded or saved session keeps the format of its file.
+    #[serde(default)]
+    pub log_format: LogFormat,
+    /// Macros for each puzzle type, keyed by the puzzle type's spec.
+    #[serde(default)]
+    pub macros: HashMap<String, Vec<Macro>>,
+    /// Piece filter presets for each puzzle type, keyed by the puzzle type's spec.
+    #[serde(default)]
+    pub filters: HashMap<String, Vec<PieceFilter>>,
+    /// Keybinds for each puzzle family, keyed by `SessionType::family`.
+    /// Families that are missing use the default keybinds.
+    #[serde(default)]
+    pub keybinds: HashMap<String, Vec<Keybind>>,
 }
 
 impl Default for Preferences {
@@ -72,20 +175,57 @@ impl Default for Preferences {
             colors: Default::default(),
             viewport_keys: false,
             concrete: Default::default(),
+            visuals: Default::default(),
             animation_length: 150.0,
+            hover_highlight: true,
+            drag_to_twist: false,
+            cameras: HashMap::new(),
+            camera_in_log: false,
+            log_format: LogFormat::Json,
+            macros: HashMap::new(),
+            filters: HashMap::new(),
+            keybinds: HashMap::new(),
         }
     }
 }
 
 impl Preferences {
-    pub fn save(&self) -> eyre::Result<()> {
-        std::fs::write(PREFS_PATH, serde_json::to_string(self)?)?;
+    /// The keybinds of the puzzle family, starting from the defaults.
+    pub fn keybinds_mut(&mut self, family: &str) -> &mut Vec<Keybind> {
+        self.keybinds
+            .entry(family.to_string())
+            .or_insert_with(|| default_keybinds(family))
+    }
+
+    pub fn keybinds(&self, family: &str) -> Vec<Keybind> {
+        self.keybinds
+            .get(family)
+            .cloned()
+            .unwrap_or_else(|| default_keybinds(family))
+    }
+
+    pub fn save(&self, path: &std::path::Path) -> eyre::Result<()> {
+        std::fs::write(path, serde_json::to_string(self)?)?;
         Ok(())
     }
 
-    pub fn load() -> eyre::Result<Self> {
-        let path = std::path::PathBuf::from(PREFS_PATH);
+    /// Reads preferences from a file that might be something else, like a dropped file.
+    /// Only a JSON object of preference fields counts, so other JSON files are not taken as
+    /// preferences with everything left as default.
+    pub fn from_json(bytes: &[u8]) -> eyre::Result<Self> {
+        let prefs: serde_json::Value = serde_json::from_slice(bytes)?;
+        let known = serde_json::to_value(Self::default())?;
+        let fields = prefs
+            .as_object()
+            .ok_or_else(|| eyre!("Preferences should be a JSON object"))?;
+        if let Some(unknown) = fields.keys().find(|field| known.get(field).is_none()) {
+            return Err(eyre!("Unknown preference {unknown}"));
+        }
+        Ok(serde_json::from_value(prefs)?)
+    }
 
+    /// Loads the preferences, or the defaults if the file does not exist.
+    pub fn load(path: &std::path::Path) -> eyre::Result<Self> {
         let file = if path.exists() {
             std::fs::File::open(path)?
         } else {
@@ -95,3 +235,24 @@ impl Preferences {
         Ok(serde_json::from_reader(reader)?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dropped_prefs() {
+        let prefs = Preferences::from_json(br#"{"animation_length": 80.0, "drag_to_twist": true}"#)
+            .unwrap();
+        assert_eq!(prefs.animation_length, 80.0);
+        assert!(prefs.drag_to_twist);
+        let saved = serde_json::to_vec(&Preferences::default()).unwrap();
+        assert!(Preferences::from_json(&saved).is_ok());
+        // a log is not preferences, even though every preference has a default
+        let log = br#"{"version": "0.4.0", "session_type": {"Cube": {"Nnn": 3}}}"#;
+        assert!(Preferences::from_json(log).is_err());
+        assert!(crate::log_format::looks_like_log(log));
+        assert!(!crate::log_format::looks_like_log(&saved));
+        assert!(Preferences::from_json(b"[1, 2]").is_err());
+    }
+}
@@ -0,0 +1,59 @@
+//! Checks that twisting and scrambling do not allocate.
+//! Counting allocations needs a `#[global_allocator]`, which replaces the allocator of the
+//! whole test binary, so it is kept in this module and only built for tests.
+//! It only counts and then hands everything to the system allocator,
+//! so other tests run the same with it.
+use crate::puzzle::common::Puzzle;
+use crate::puzzle::cube::CubeRay;
+use crate::puzzle::octa::OctaRay;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+/// Counts the allocations made by each thread, so that tests running
+/// at the same time do not count each other's.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// The number of allocations `f` makes on this thread.
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn twists_do_not_allocate() {
+    use crate::puzzle::cube::name::*;
+
+    let mut cube = Puzzle::<CubeRay>::make_nnn(7);
+    let grip = cube.grips[1];
+    assert_eq!(allocations(|| cube.twist((R, 1), &grip)), 0);
+    // the thread's random number generator is set up on first use
+    cube.scramble();
+    assert_eq!(allocations(|| cube.scramble()), 0);
+
+    let mut fto = Puzzle::<OctaRay>::make_nnn(4);
+    assert_eq!(allocations(|| fto.scramble()), 0);
+}
@@ -1,6 +1,7 @@
 use crate::util::color::Color;
 use enum_map::{Enum, EnumMap};
 use rand;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::iter::zip;
 use std::ops::{Add, Mul, Neg, Sub};
@@ -132,7 +133,7 @@ impl Sign {
 }
 
 /// A set of rays that align with the turns of the puzzle.
-/// Changing the order of them will change the log files.
+/// Logs save rays by name, but `log_format` keeps the order they had for older logs.
 pub trait RaySystem
 where
     Self: 'static
@@ -147,9 +148,9 @@ where
         + enum_map::EnumArray<Color>
         + fmt::Display,
 {
-    /// Returns a list of rays that make up the vector. Should
+    /// Returns the rays that make up the axis. Should
     /// return the same order for each axis.
-    fn get_axis(&self) -> Vec<Self>;
+    fn get_axis(&self) -> [Self; AXIS_RAYS];
     /// Turns the ray system one unit clockwise about ray's axis and returns the new ray
     /// that occupies self's direction.
     /// Should return the same value for any ray with the same axis.
@@ -171,14 +172,13 @@ where
     const AXIS_HEADS: &'static [Self];
     /// Hamiltonian cycle for symmetry group
     const CYCLE: &'static [(Self, i8)];
+    /// Names for whole-puzzle rotations, like `x` for the cube,
+    /// and the ray each one turns like.
+    const ROTATION_NAMES: &'static [(&'static str, Self)] = &[];
 
     /// Uniform selection
     fn choose<R: rand::Rng>(rng: &mut R) -> Self {
-        use rand::seq::SliceRandom;
-        *enum_iter::<Self>()
-            .collect::<Vec<_>>()
-            .choose(rng)
-            .expect("ray system should not be empty")
+        Self::from_usize(rng.gen_range(0..Self::LENGTH))
     }
 
     // Short name for ray
@@ -187,8 +187,28 @@ where
     fn from_name(name: &str) -> Option<Self> {
         enum_iter::<Self>().find(|ray: &Self| ray.name() == name)
     }
+
+    /// The position of the ray in its axis.
+    fn axis_index(&self) -> usize {
+        self.get_axis()
+            .iter()
+            .position(|r| r == self)
+            .expect("rays are always in their axes")
+    }
+
+    /// The order of one clockwise turn when looking at the ray.
+    /// Turns are clockwise around the axis head.
+    fn clockwise(&self) -> i8 {
+        (-1i8).pow(self.axis_index() as u32)
+    }
 }
 
+/// The number of rays on every axis, one on each side of the puzzle.
+pub const AXIS_RAYS: usize = 2;
+
+/// The layer of a piece on each ray of an axis, in `get_axis` order.
+pub type Grip = [i8; AXIS_RAYS];
+
 /// A single piece of an abstract laminated puzzle.
 #[derive(Debug)]
 pub struct Piece<Ray>
@@ -203,7 +223,7 @@ where
 }
 
 impl<Ray: RaySystem> Piece<Ray> {
-    pub fn make_solved(axis_layers: Vec<Vec<i8>>) -> Self {
+    pub fn make_solved(axis_layers: impl IntoIterator<Item = Grip>) -> Self {
         let mut layers = EnumMap::from_fn(|_ray| 0);
         for (axh, axl) in zip(Ray::AXIS_HEADS, axis_layers) {
             for (ray, layer) in zip(axh.get_axis(), axl) {
@@ -227,19 +247,16 @@ impl<Ray: RaySystem> Piece<Ray> {
         self.orientation.iter().all(|(pos, &cur)| pos == cur)
     }
 
-    pub fn grip_on_axis(&self, ray: Ray) -> Vec<i8> {
-        ray.get_axis()
-            .iter()
-            .map(|&r| self.layers[self.orientation[r]])
-            .collect()
+    pub fn grip_on_axis(&self, ray: Ray) -> Grip {
+        ray.get_axis().map(|r| self.layers[self.orientation[r]])
     }
 
-    pub fn grip_on_axis_solved(&self, ray: Ray) -> Vec<i8> {
-        ray.get_axis().iter().map(|&r| self.layers[r]).collect()
+    pub fn grip_on_axis_solved(&self, ray: Ray) -> Grip {
+        ray.get_axis().map(|r| self.layers[r])
     }
 
-    pub fn twist(&mut self, (ray, order): (Ray, i8), grip: &[i8]) -> bool {
-        if &self.grip_on_axis(ray)[..] == grip {
+    pub fn twist(&mut self, (ray, order): (Ray, i8), grip: &Grip) -> bool {
+        if &self.grip_on_axis(ray) == grip {
             let new_orientation = EnumMap::from_fn(|r: Ray| self.orientation[r.turn((ray, order))]);
             self.orientation = new_orientation;
             /*for (_, cur) in self.orientation.iter_mut() {
@@ -271,30 +288,142 @@ impl<Ray: RaySystem + fmt::Display> fmt::Display for Piece<Ray> {
     }
 }
 
+/// A turn of the puzzle together with the grips it turns.
+pub type Twist<Ray> = ((Ray, i8), Vec<Grip>);
+
+/// An orientation by ray names: for each ray, the ray now occupying its direction.
+/// Unlike a list in enum order it does not depend on the order of the rays,
+/// so saved puzzle states use it.
+pub type NamedOrientation = BTreeMap<String, String>;
+
+pub fn orientation_to_names<Ray: RaySystem>(ori: &EnumMap<Ray, Ray>) -> NamedOrientation {
+    ori.iter()
+        .map(|(ray, occupant)| (ray.name(), occupant.name()))
+        .collect()
+}
+
+/// Reads a `NamedOrientation`, which has to name every ray once.
+pub fn orientation_from_names<Ray: RaySystem>(
+    names: &NamedOrientation,
+) -> Option<EnumMap<Ray, Ray>> {
+    if names.len() != Ray::LENGTH {
+        return None;
+    }
+    let mut ori = EnumMap::from_fn(|ray| ray);
+    for (ray, occupant) in names {
+        ori[Ray::from_name(ray)?] = Ray::from_name(occupant)?;
+    }
+    Some(ori)
+}
+
 /// Abstract laminated puzzle.
 /// I assume the pieces are always in order of their layers.
+/// A sparse puzzle only has some of the positions, see `make_sparse`.
 #[derive(Debug)]
 pub struct Puzzle<Ray: RaySystem> {
-    pub grips: Vec<Vec<i8>>,
+    pub grips: Vec<Grip>,
+    /// Only change these through `twist` and `set_orientations`,
+    /// which keep `permutation` and `layer_pieces` up to date.
     pub pieces: Vec<Piece<Ray>>,
+    /// The index of the solved position of each piece, in increasing order.
+    solved_positions: Vec<usize>,
+    /// For each position in `solved_positions`, the index in `pieces` of the piece there.
+    permutation: Vec<usize>,
+    /// For each axis (in `AXIS_HEADS` order) and grip (in `grips` order),
+    /// the indices in `pieces` of the pieces currently in that layer.
+    layer_pieces: Vec<Vec<Vec<usize>>>,
+    /// For each piece and axis, at `piece * AXIS_HEADS.len() + axis`,
+    /// the index in `grips` of the layer the piece is in and its place in that layer's list.
+    piece_layers: Vec<(usize, usize)>,
+    /// Each grip with its index in `grips`, sorted by grip for binary search.
+    sorted_grips: Vec<(Grip, usize)>,
 }
 
 impl<Ray: RaySystem> Puzzle<Ray> {
-    pub fn piece_count(&self) -> usize {
+    /// The number of positions on the puzzle with every piece.
+    pub fn position_count(&self) -> usize {
         self.grips.len().pow(Ray::AXIS_HEADS.len() as u32)
     }
 
-    pub fn make_solved(grips: Vec<Vec<i8>>) -> Self {
+    pub fn make_solved(grips: Vec<Grip>) -> Self {
+        let position_count = grips.len().pow(Ray::AXIS_HEADS.len() as u32);
+        Self::make_with_positions(grips, (0..position_count).collect())
+    }
+
+    /// Makes a solved puzzle with `layers` layers on each axis, like an NxNxN cube.
+    #[cfg(test)]
+    pub fn make_nnn(layers: i8) -> Self {
+        Self::make_solved(
+            (-layers + 1..=layers - 1)
+                .step_by(2)
+                .map(|k| [k, -k])
+                .collect(),
+        )
+    }
+
+    /// Makes a puzzle with only the pieces solved at these positions,
+    /// and the ones that twists can bring there.
+    /// Pieces that no sticker shows do not have to exist, which saves a lot on big puzzles.
+    pub fn make_sparse(grips: Vec<Grip>, positions: impl IntoIterator<Item = usize>) -> Self {
+        let empty = Self::make_with_positions(grips, Vec::new());
+        let mut all_positions = BTreeSet::new();
+        for position in positions {
+            if all_positions.contains(&position) {
+                continue;
+            }
+            // a twist moves each piece like some rotation of the whole puzzle,
+            // and the cycle goes through every rotation
+            let mut layers = empty.index_to_solved_piece(position).layers;
+            all_positions.insert(position);
+            for &(ray, order) in Ray::CYCLE {
+                layers = EnumMap::from_fn(|r: Ray| layers[r.turn((ray, -order))]);
+                let piece = Piece::make_solved_from_layers(enum_map_clone(&layers));
+                all_positions.insert(empty.piece_to_index_solved(&piece));
+            }
+        }
+        Self::make_with_positions(empty.grips, all_positions.into_iter().collect())
+    }
+
+    fn make_with_positions(grips: Vec<Grip>, solved_positions: Vec<usize>) -> Self {
         let mut new = Self {
-            grips: grips.clone(),
+            grips,
             pieces: Vec::new(),
+            solved_positions,
+            permutation: Vec::new(),
+            layer_pieces: Vec::new(),
+            piece_layers: Vec::new(),
+            sorted_grips: Vec::new(),
         };
-        new.pieces = (0..new.piece_count())
-            .map(|i| new.index_to_solved_piece(i))
+        new.pieces = new
+            .solved_positions
+            .iter()
+            .map(|&position| new.index_to_solved_piece(position))
             .collect();
+        new.reindex();
         new
     }
 
+    /// Whether the puzzle only has some of the positions.
+    pub fn is_sparse(&self) -> bool {
+        self.solved_positions.len() != self.position_count()
+    }
+
+    /// The index of the solved position of each piece, in increasing order.
+    pub fn solved_positions(&self) -> &[usize] {
+        &self.solved_positions
+    }
+
+    /// The index in `pieces` of the piece solved at the position,
+    /// which is also where the position is in `permutation`.
+    /// None if the puzzle does not have the position.
+    pub fn position_slot(&self, position: usize) -> Option<usize> {
+        if self.is_sparse() {
+            self.solved_positions.binary_search(&position).ok()
+        } else {
+            (position < self.solved_positions.len()).then_some(position)
+        }
+    }
+
     /// Checks whether the puzzle is solved, i.e. whether all pieces
     /// are in the same orientation.
     pub fn is_solved(&self) -> bool {
@@ -305,9 +434,95 @@ impl<Ray: RaySystem> Puzzle<Ray> {
     }
 
     /// Applies the twist to the puzzle.
-    pub fn twist(&mut self, (ray, order): (Ray, i8), grip: &[i8]) {
+    /// Only the pieces in the twisted layer are looked at.
+    pub fn twist(&mut self, (ray, order): (Ray, i8), grip: &Grip) {
+        let axis = Ray::AXIS_HEADS
+            .iter()
+            .position(|head| head.get_axis() == ray.get_axis())
+            .expect("every ray has an axis head");
+        let Some(grip_index) = self.grips.iter().position(|gr| gr == grip) else {
+            return;
+        };
+
+        // The pieces stay in the twisted layer but can move between the layers
+        // of the other axes. They all leave their layers before any of them join
+        // their new ones, so no list grows past its size and needs to reallocate.
+        let layer = std::mem::take(&mut self.layer_pieces[axis][grip_index]);
+        for &i in &layer {
+            for other_axis in (0..Ray::AXIS_HEADS.len()).filter(|&j| j != axis) {
+                self.leave_layer(i, other_axis);
+            }
+        }
+        for &i in &layer {
+            self.pieces[i].twist((ray, order), grip);
+            for other_axis in (0..Ray::AXIS_HEADS.len()).filter(|&j| j != axis) {
+                self.join_layer(i, other_axis);
+            }
+            // the twisted pieces fill each other's old positions,
+            // so the permutation is right again once they have all moved
+            let position = (0..Ray::AXIS_HEADS.len())
+                .map(|j| {
+                    self.piece_layers[i * Ray::AXIS_HEADS.len() + j].0
+                        * self.grips.len().pow(j as u32)
+                })
+                .sum::<usize>();
+            let slot = self
+                .position_slot(position)
+                .expect("twists only move pieces to positions the puzzle has");
+            self.permutation[slot] = i;
+        }
+        self.layer_pieces[axis][grip_index] = layer;
+    }
+
+    /// The indices in `pieces` of the pieces currently in the layer
+    /// of the grip on the ray's axis.
+    pub fn pieces_in_layer(&self, ray: Ray, grip: &Grip) -> &[usize] {
+        let axis = Ray::AXIS_HEADS
+            .iter()
+            .position(|head| head.get_axis() == ray.get_axis());
+        let grip_index = self.grips.iter().position(|gr| gr == grip);
+        match (axis, grip_index) {
+            (Some(axis), Some(grip_index)) => &self.layer_pieces[axis][grip_index],
+            _ => &[],
+        }
+    }
+
+    /// The index in `grips` of the grip the piece is currently in on the axis.
+    fn grip_index(&self, piece: &Piece<Ray>, axis: usize) -> usize {
+        let grip = piece.grip_on_axis(Ray::AXIS_HEADS[axis]);
+        let found = self.sorted_grips.binary_search_by_key(&grip, |&(gr, _)| gr);
+        self.sorted_grips[found.expect("grips should all exist because the piece should be valid")]
+            .1
+    }
+
+    fn join_layer(&mut self, piece: usize, axis: usize) {
+        let grip_index = self.grip_index(&self.pieces[piece], axis);
+        let layer = &mut self.layer_pieces[axis][grip_index];
+        self.piece_layers[piece * Ray::AXIS_HEADS.len() + axis] = (grip_index, layer.len());
+        layer.push(piece);
+    }
+
+    fn leave_layer(&mut self, piece: usize, axis: usize) {
+        let axes = Ray::AXIS_HEADS.len();
+        let (grip_index, slot) = self.piece_layers[piece * axes + axis];
+        let layer = &mut self.layer_pieces[axis][grip_index];
+        layer.swap_remove(slot);
+        if let Some(&moved) = layer.get(slot) {
+            self.piece_layers[moved * axes + axis].1 = slot;
+        }
+    }
+
+    /// Rebuilds the lookups from the grips and the pieces.
+    fn reindex(&mut self) {
+        self.sorted_grips = zip(self.grips.iter().copied(), 0..).collect();
+        self.sorted_grips.sort();
+        self.permutation = self.compute_permutation();
+        self.layer_pieces = vec![vec![Vec::new(); self.grips.len()]; Ray::AXIS_HEADS.len()];
+        self.piece_layers = vec![(0, 0); self.pieces.len() * Ray::AXIS_HEADS.len()];
         for i in 0..self.pieces.len() {
-            let _piece_twisted = self.pieces[i].twist((ray, order), grip);
+            for axis in 0..Ray::AXIS_HEADS.len() {
+                self.join_layer(i, axis);
+            }
         }
     }
 
@@ -316,26 +531,23 @@ impl<Ray: RaySystem> Puzzle<Ray> {
         let grip_count: usize = self.grips.len();
         Piece::make_solved(
             (0..Ray::AXIS_HEADS.len())
-                .map(|j| self.grips[i / grip_count.pow(j as u32) % grip_count].clone())
-                .collect(),
+                .map(|j| self.grips[i / grip_count.pow(j as u32) % grip_count]),
         )
     }
 
     /// Gets the index of the current position of the piece.
     pub fn piece_to_index(&self, piece: &Piece<Ray>) -> usize {
-        Ray::AXIS_HEADS
-            .iter()
-            .enumerate()
-            .map(|(j, &r)| {
-                self.grips
-                    .iter()
-                    .position(|gr| &piece.grip_on_axis(r)[..] == gr)
-                    .expect("grips should all exist because the piece should be valid")
-                    * self.grips.len().pow(j as u32)
-            })
+        (0..Ray::AXIS_HEADS.len())
+            .map(|j| self.grip_index(piece, j) * self.grips.len().pow(j as u32))
             .sum()
     }
 
+    /// The index in `solved_positions` of the position the piece at this index is in now.
+    pub fn piece_slot(&self, piece: usize) -> usize {
+        self.position_slot(self.piece_to_index(&self.pieces[piece]))
+            .expect("pieces should only be in positions the puzzle has")
+    }
+
     /// Gets the index of the solved position of the piece.
     pub fn piece_to_index_solved(&self, piece: &Piece<Ray>) -> usize {
         Ray::AXIS_HEADS
@@ -344,18 +556,39 @@ impl<Ray: RaySystem> Puzzle<Ray> {
             .map(|(j, &r)| {
                 self.grips
                     .iter()
-                    .position(|gr| &piece.grip_on_axis_solved(r)[..] == gr)
+                    .position(|gr| &piece.grip_on_axis_solved(r) == gr)
                     .expect("grips should all exist because the piece should be valid")
                     * self.grips.len().pow(j as u32)
             })
             .sum()
     }
 
-    pub fn permutation(&self) -> Vec<usize> {
-        let mut permutation = vec![0; self.piece_count()];
+    /// Whether turning all these grips turns the whole puzzle.
+    pub fn is_rotation(&self, grips: &[Grip]) -> bool {
+        self.grips.iter().all(|grip| grips.contains(grip))
+    }
+
+    /// Returns the grips in order of depth as seen from the face,
+    /// starting with the outermost layer.
+    pub fn grips_from_face(&self, face: Ray) -> Vec<Grip> {
+        let axis_index = face.axis_index();
+        let mut grips = self.grips.clone();
+        grips.sort_by_key(|grip| -grip[axis_index]);
+        grips
+    }
+
+    /// For each position in `solved_positions`, the index in `pieces` of the piece there.
+    pub fn permutation(&self) -> &[usize] {
+        &self.permutation
+    }
+
+    fn compute_permutation(&self) -> Vec<usize> {
+        let mut permutation = vec![0; self.pieces.len()];
         for i in 0..self.pieces.len() {
-            let piece_index = self.piece_to_index(&self.pieces[i]);
-            permutation[piece_index] = i;
+            let slot = self
+                .position_slot(self.piece_to_index(&self.pieces[i]))
+                .expect("pieces should only be in positions the puzzle has");
+            permutation[slot] = i;
         }
         permutation
     }
@@ -371,20 +604,43 @@ impl<Ray: RaySystem> Puzzle<Ray> {
         for (piece, ori) in self.pieces.iter_mut().zip(oris) {
             piece.orientation = enum_map_clone(ori);
         }
+        self.reindex();
+    }
+
+    /// Sets the orientations of the pieces solved at the positions,
+    /// skipping positions the puzzle does not have. The other pieces are solved.
+    /// This moves orientations between sparse and full puzzles.
+    pub fn set_position_orientations(&mut self, positions: &[usize], oris: &[EnumMap<Ray, Ray>]) {
+        for piece in &mut self.pieces {
+            piece.orientation = EnumMap::from_fn(|ray| ray);
+        }
+        for (&position, ori) in zip(positions, oris) {
+            if let Some(slot) = self.position_slot(position) {
+                self.pieces[slot].orientation = enum_map_clone(ori);
+            }
+        }
+        self.reindex();
+    }
+
+    /// Puts every piece back in its solved position.
+    pub fn reset(&mut self) {
+        self.set_position_orientations(&[], &[]);
     }
 
     pub fn scramble(&mut self) {
+        self.scramble_with(&mut rand::thread_rng());
+    }
+
+    /// Scrambles with random twists from `rng`, so a seeded `rng` always scrambles the same way.
+    pub fn scramble_with<R: rand::Rng>(&mut self, rng: &mut R) {
         use rand::seq::SliceRandom;
-        use rand::Rng;
 
-        let mut rng = rand::thread_rng();
         for _ in 0..1000 {
-            let ray = Ray::choose(&mut rng);
-            let grip = self.grips[..]
-                .choose(&mut rng)
-                .expect("ray system should not be empty")
-                .to_vec();
-            self.twist((ray, rng.gen_range(0..Ray::order())), &grip[..]);
+            let ray = Ray::choose(rng);
+            let grip = *self.grips[..]
+                .choose(rng)
+                .expect("ray system should not be empty");
+            self.twist((ray, rng.gen_range(0..Ray::order())), &grip);
         }
     }
 }
@@ -395,9 +651,9 @@ pub mod ray_system_tests {
 
     fn axes_all_same_order<Ray: RaySystem + std::fmt::Debug>() {
         for ray in enum_iter::<Ray>() {
-            let axis = ray.get_axis().clone();
+            let axis = ray.get_axis();
             for ray2 in &axis {
-                let axis2 = ray2.get_axis().clone();
+                let axis2 = ray2.get_axis();
                 assert_eq!(
                     axis, axis2,
                     "rays {:?} and {:?} have different axes",
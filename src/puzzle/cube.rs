@@ -1,16 +1,16 @@
 use enum_map::Enum;
 use std::fmt;
 
-use crate::puzzle::common::RaySystem;
 pub use crate::puzzle::common::{Basis, BasisDiff, Sign};
+use crate::puzzle::common::{RaySystem, AXIS_RAYS};
 
 /// +X: R, +Y: B, +Z: U
 #[derive(Debug, Enum, Clone, Copy, PartialEq, Eq)]
 pub struct CubeRay(pub Basis, pub Sign);
 
 impl RaySystem for CubeRay {
-    fn get_axis(&self) -> Vec<Self> {
-        vec![CubeRay(self.0, Sign::Pos), CubeRay(self.0, Sign::Neg)]
+    fn get_axis(&self) -> [Self; AXIS_RAYS] {
+        [CubeRay(self.0, Sign::Pos), CubeRay(self.0, Sign::Neg)]
     }
 
     fn turn_one(&self, axis: Self) -> Self {
@@ -46,6 +46,9 @@ impl RaySystem for CubeRay {
         ]
     };
 
+    const ROTATION_NAMES: &'static [(&'static str, Self)] =
+        &[("x", name::R), ("y", name::U), ("z", name::F)];
+
     fn name(&self) -> String {
         match self {
             CubeRay(Basis::X, Sign::Pos) => "R".to_string(),
@@ -91,7 +94,7 @@ mod tests {
     /// Applies one turn and asserts that it is unsolved.
     #[test]
     fn one_turn() {
-        let mut puzzle = Puzzle::make_solved(vec![vec![0, 0], vec![1, 0], vec![0, 1]]);
+        let mut puzzle = Puzzle::make_solved(vec![[0, 0], [1, 0], [0, 1]]);
         puzzle.twist((R, 1), &[1, 0]);
         assert!(!puzzle.is_solved());
     }
@@ -99,7 +102,7 @@ mod tests {
     /// Applies (R U R' U')6 to the 3x3x3 and asserts that it is solved.
     #[test]
     fn six_sexy() {
-        let mut puzzle = Puzzle::make_solved(vec![vec![0, 0], vec![1, 0], vec![0, 1]]);
+        let mut puzzle = Puzzle::make_solved(vec![[0, 0], [1, 0], [0, 1]]);
         for _ in 0..6 {
             puzzle.twist((R, 1), &[1, 0]);
             puzzle.twist((R, 1), &[1, 0]);
@@ -108,4 +111,81 @@ mod tests {
         }
         assert!(puzzle.is_solved());
     }
+
+    /// Checks the permutation kept by the puzzle against the pieces' positions.
+    #[test]
+    fn permutation_follows_twists() {
+        let grips = vec![[-3, 3], [-1, 1], [1, -1], [3, -3]];
+        let assert_permutation = |puzzle: &Puzzle<CubeRay>| {
+            for (i, piece) in puzzle.pieces.iter().enumerate() {
+                assert_eq!(puzzle.permutation()[puzzle.piece_to_index(piece)], i);
+            }
+        };
+
+        let mut puzzle = Puzzle::make_solved(grips.clone());
+        assert_permutation(&puzzle);
+        puzzle.scramble();
+        assert_permutation(&puzzle);
+
+        let mut copy = Puzzle::make_solved(grips);
+        copy.set_orientations(&puzzle.orientations());
+        assert_eq!(copy.permutation(), puzzle.permutation());
+    }
+
+    /// Checks the pieces listed in each layer against the pieces' grips.
+    #[test]
+    fn layers_follow_twists() {
+        let mut puzzle = Puzzle::make_solved(vec![[-2, 2], [0, 0], [2, -2]]);
+        puzzle.scramble();
+        for &axis in CubeRay::AXIS_HEADS {
+            for grip in &puzzle.grips {
+                let mut listed = puzzle.pieces_in_layer(axis, grip).to_vec();
+                listed.sort();
+                let in_layer: Vec<usize> = (0..puzzle.pieces.len())
+                    .filter(|&i| &puzzle.pieces[i].grip_on_axis(axis) == grip)
+                    .collect();
+                assert_eq!(listed, in_layer);
+            }
+        }
+        assert!(puzzle.pieces_in_layer(L, &[9, 9]).is_empty());
+    }
+
+    /// Twists a sparse puzzle with only the corners alongside the full puzzle.
+    #[test]
+    fn sparse_corners() {
+        let grips = vec![[-2, 2], [0, 0], [2, -2]];
+        let mut full = Puzzle::make_solved(grips.clone());
+        let corner = full.piece_to_index_solved(&full.pieces[0]);
+        let mut sparse = Puzzle::make_sparse(grips, [corner]);
+        assert!(sparse.is_sparse());
+        assert_eq!(sparse.pieces.len(), 8);
+
+        for twist in [(R, 1), (U, -1), (F, 2), (D, 1)] {
+            full.twist(twist, &[2, -2]);
+            sparse.twist(twist, &[2, -2]);
+        }
+        for (i, &position) in sparse.solved_positions().iter().enumerate() {
+            let full_index = full.permutation()[position];
+            let sparse_index = sparse.permutation()[i];
+            assert_eq!(
+                full.pieces[full_index].layers,
+                sparse.pieces[sparse_index].layers
+            );
+        }
+        for (slot, &piece) in sparse.permutation().iter().enumerate() {
+            assert_eq!(sparse.piece_slot(piece), slot);
+        }
+
+        // orientations move between the sparse and the full puzzle by position
+        let mut copy = Puzzle::make_solved(vec![[-2, 2], [0, 0], [2, -2]]);
+        copy.set_position_orientations(sparse.solved_positions(), &sparse.orientations());
+        for &position in sparse.solved_positions() {
+            assert_eq!(
+                copy.pieces[position].orientation,
+                full.pieces[position].orientation
+            );
+        }
+        sparse.reset();
+        assert!(sparse.is_solved());
+    }
 }
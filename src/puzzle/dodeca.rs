@@ -1,8 +1,8 @@
 use enum_map::Enum;
 use std::fmt;
 
-use crate::puzzle::common::RaySystem;
 pub use crate::puzzle::common::{Basis, BasisDiff, Sign};
+use crate::puzzle::common::{RaySystem, AXIS_RAYS};
 
 /// DodecaRay(a, ±₁, ±₂) => φ ±₁a⁺ + ±₂a⁺⁺
 #[derive(Debug, Enum, Clone, Copy, PartialEq, Eq)]
@@ -36,8 +36,8 @@ impl DodecaRay {
 }
 
 impl RaySystem for DodecaRay {
-    fn get_axis(&self) -> Vec<Self> {
-        vec![
+    fn get_axis(&self) -> [Self; AXIS_RAYS] {
+        [
             DodecaRay(self.0, Sign::Pos, self.1 * self.2),
             DodecaRay(self.0, Sign::Neg, -self.1 * self.2),
         ]
@@ -0,0 +1,118 @@
+use crate::puzzle::common::*;
+use serde::Deserialize;
+use serde::Serialize;
+use std::fmt;
+
+/// How pieces that do not pass a filter are drawn.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FilterStyle {
+    #[default]
+    Hide,
+    Gray,
+    Translucent,
+}
+
+impl FilterStyle {
+    pub const ALL: [Self; 3] = [Self::Hide, Self::Gray, Self::Translucent];
+}
+
+impl fmt::Display for FilterStyle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Hide => write!(f, "Hide"),
+            Self::Gray => write!(f, "Gray out"),
+            Self::Translucent => write!(f, "Translucent"),
+        }
+    }
+}
+
+/// Which pieces pass a filter by whether they are solved.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SolvedFilter {
+    #[default]
+    Any,
+    Solved,
+    Unsolved,
+}
+
+/// A filter that picks out the pieces to focus on.
+/// Every condition has to hold for a piece to pass, and an empty condition always holds.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct PieceFilter {
+    pub name: String,
+    /// Piece types from `symmetry::piece_type` that pass.
+    #[serde(default)]
+    pub types: Vec<Vec<i8>>,
+    #[serde(default)]
+    pub solved: SolvedFilter,
+    /// Grips the piece has to be in right now, as (index in `AXIS_HEADS`, grip).
+    #[serde(default)]
+    pub grips: Vec<(usize, Grip)>,
+    /// How the pieces that do not pass are drawn.
+    #[serde(default)]
+    pub style: FilterStyle,
+}
+
+impl PieceFilter {
+    /// Whether the piece passes. `piece_type` is the type of the piece.
+    pub fn passes<Ray: RaySystem>(&self, piece: &Piece<Ray>, piece_type: &[i8]) -> bool {
+        let type_passes = self.types.is_empty() || self.types.iter().any(|t| t == piece_type);
+        let solved_passes = match self.solved {
+            SolvedFilter::Any => true,
+            SolvedFilter::Solved => piece.is_solved(),
+            SolvedFilter::Unsolved => !piece.is_solved(),
+        };
+        let grips_pass = self.grips.iter().all(|(axis_index, grip)| {
+            Ray::AXIS_HEADS
+                .get(*axis_index)
+                .is_some_and(|&axis| &piece.grip_on_axis(axis) == grip)
+        });
+        type_passes && solved_passes && grips_pass
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzle::cube::{name::*, CubeRay};
+    use crate::puzzle::symmetry::piece_type;
+
+    #[test]
+    fn filters() {
+        let mut puzzle: Puzzle<CubeRay> = Puzzle::make_solved(vec![[-2, 2], [0, 0], [2, -2]]);
+        puzzle.twist((R, 1), &[2, -2]);
+        let passing = |filter: &PieceFilter| {
+            puzzle
+                .pieces
+                .iter()
+                .filter(|piece| filter.passes(piece, &piece_type(&piece.layers)))
+                .count()
+        };
+
+        assert_eq!(passing(&PieceFilter::default()), 27);
+
+        let corner = piece_type(&puzzle.pieces[0].layers);
+        let corners = PieceFilter {
+            types: vec![corner],
+            ..Default::default()
+        };
+        assert_eq!(passing(&corners), 8);
+
+        let unsolved_corners = PieceFilter {
+            solved: SolvedFilter::Unsolved,
+            ..corners.clone()
+        };
+        assert_eq!(passing(&unsolved_corners), 4);
+
+        // the R layer, found by where the pieces are now
+        let r_axis = CubeRay::AXIS_HEADS
+            .iter()
+            .position(|&ray| ray == R)
+            .unwrap();
+        let r_layer = PieceFilter {
+            grips: vec![(r_axis, [2, -2])],
+            ..Default::default()
+        };
+        assert_eq!(passing(&r_layer), 9);
+    }
+}
@@ -0,0 +1,103 @@
+use crate::puzzle::common::*;
+use crate::puzzle::simplify::{normalize_order, simplify};
+use std::fmt;
+
+/// Move counts of a twist sequence in several metrics.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct MoveCount {
+    /// Execution turn metric: every twist counts as one move.
+    pub etm: usize,
+    /// Quarter turn metric: every contiguous block of layers counts once per unit turn.
+    pub qtm: usize,
+    /// Laminated turn metric: every contiguous block of layers counts as one move,
+    /// no matter the angle. Slices and wide turns count as one, but turning
+    /// two separate blocks of layers counts as two.
+    pub ltm: usize,
+}
+
+/// The number of contiguous blocks of layers that the grips make up.
+fn layer_blocks<Ray: RaySystem>(puzzle: &Puzzle<Ray>, twist: &Twist<Ray>) -> usize {
+    let ((ray, _), grips) = twist;
+    let selected: Vec<bool> = puzzle
+        .grips_from_face(*ray)
+        .iter()
+        .map(|grip| grips.contains(grip))
+        .collect();
+    let starts = selected.windows(2).filter(|w| !w[0] && w[1]).count();
+    starts + usize::from(selected.first() == Some(&true))
+}
+
+impl MoveCount {
+    /// Counts the moves of the sequence after simplifying it.
+    /// Whole-puzzle rotations are not counted.
+    pub fn count<Ray: RaySystem>(puzzle: &Puzzle<Ray>, twists: &[Twist<Ray>]) -> Self {
+        let mut count = MoveCount::default();
+        for twist in simplify(puzzle, twists) {
+            if puzzle.is_rotation(&twist.1) {
+                continue;
+            }
+            let blocks = layer_blocks(puzzle, &twist);
+            let turns = normalize_order::<Ray>(twist.0 .1).unsigned_abs() as usize;
+            count.etm += 1;
+            count.qtm += turns * blocks;
+            count.ltm += blocks;
+        }
+        count
+    }
+}
+
+impl fmt::Display for MoveCount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ETM {}  QTM {}  LTM {}", self.etm, self.qtm, self.ltm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzle::cube::CubeRay;
+    use crate::puzzle::notation::parse;
+
+    #[test]
+    fn counts() {
+        let puzzle: Puzzle<CubeRay> = Puzzle::make_nnn(5);
+        let count = |text| MoveCount::count(&puzzle, &parse(&puzzle, text).unwrap());
+
+        assert_eq!(
+            count("R U' R2"),
+            MoveCount {
+                etm: 3,
+                qtm: 4,
+                ltm: 3
+            }
+        );
+        // slices and wide turns
+        assert_eq!(
+            count("2-4R 1-2U2"),
+            MoveCount {
+                etm: 2,
+                qtm: 3,
+                ltm: 2
+            }
+        );
+        // two separate blocks in one twist
+        assert_eq!(
+            count("{1,3}F"),
+            MoveCount {
+                etm: 1,
+                qtm: 2,
+                ltm: 2
+            }
+        );
+        // cancellation
+        assert_eq!(count("R U U' R'"), MoveCount::default());
+        // `Session::do_inverse` keeps the twist and adds one back past it
+        assert_eq!(count("U R R2'"), count("U R'"));
+        // rotations
+        assert_eq!(count("x y2 1-5F"), MoveCount::default());
+        // rotations next to turns on the same axis
+        assert_eq!(count("R x"), count("R"));
+        assert_eq!(count("x R x'"), count("R"));
+        assert_eq!(count("R x R"), count("R2"));
+    }
+}
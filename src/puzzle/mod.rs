@@ -1,5 +1,12 @@
+#[cfg(test)]
+mod alloc_count;
 pub mod common;
 pub mod cube;
 pub mod dodeca;
+pub mod filter;
+pub mod metric;
+pub mod notation;
 pub mod octa;
 pub mod r_dodeca;
+pub mod simplify;
+pub mod symmetry;
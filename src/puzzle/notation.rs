@@ -0,0 +1,502 @@
+use crate::puzzle::common::*;
+use crate::util::enum_iter;
+use eyre::eyre;
+use itertools::Itertools;
+
+/*
+Notation for twist sequences.
+
+A move is an optional layer prefix, the name of the face, an optional count and an optional
+prime, for example `R`, `BU'`, `2R2`, `2-3PB'` or `{1,3}U`. Layers are counted from the face,
+starting at 1 for the outermost layer. Moves without a layer prefix turn the outermost layer.
+Turns are clockwise when looking at the face.
+
+Whole-puzzle rotations are written with `@` before the face, like `@R` or `@BU'`.
+Some puzzles have their own names for rotations, like `x`, `y` and `z` on the cube.
+
+Moves can be grouped with `(A B)`, and written as a commutator `[A, B]` or a conjugate
+`[A: B]`. Any group can be followed by a count and a prime.
+*/
+
+/// Parses a list of layer ranges like `2-5` or `1, 3-4`, 1-indexed.
+pub fn parse_layer_list(text: &str) -> eyre::Result<Vec<usize>> {
+    let number = |s: &str| {
+        s.trim()
+            .parse::<usize>()
+            .map_err(|_| eyre!("invalid layer {}", s.trim()))
+    };
+    let mut layers = vec![];
+    for range in text.split(',').filter(|range| !range.trim().is_empty()) {
+        let (start, end) = match range.split_once('-') {
+            Some((start, end)) => (number(start)?, number(end)?),
+            None => (number(range)?, number(range)?),
+        };
+        if start == 0 || end < start {
+            return Err(eyre!("invalid layer range {}", range.trim()));
+        }
+        layers.extend(start..=end);
+    }
+    Ok(layers.into_iter().unique().collect())
+}
+
+/// Inverts a twist sequence.
+pub fn invert<Ray: RaySystem>(twists: &[Twist<Ray>]) -> Vec<Twist<Ray>> {
+    twists
+        .iter()
+        .rev()
+        .map(|((ray, order), grips)| ((*ray, -order), grips.clone()))
+        .collect()
+}
+
+/// The most twists an algorithm can expand to, so that a large count
+/// is reported instead of using up all the memory.
+const MAX_TWISTS: usize = 10_000;
+
+fn repeat<Ray: RaySystem>(twists: Vec<Twist<Ray>>, count: i8, prime: bool) -> Vec<Twist<Ray>> {
+    let once = if prime { invert(&twists) } else { twists };
+    (0..count).flat_map(|_| once.iter().cloned()).collect()
+}
+
+struct Parser<'a, Ray: RaySystem> {
+    chars: Vec<char>,
+    pos: usize,
+    puzzle: &'a Puzzle<Ray>,
+    /// Ray names, longest first so that e.g. `BU` is not read as `B`.
+    names: Vec<(String, Ray)>,
+}
+
+impl<'a, Ray: RaySystem> Parser<'a, Ray> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> eyre::Result<()> {
+        self.skip_whitespace();
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{c}'")))
+        }
+    }
+
+    fn error(&self, message: &str) -> eyre::Report {
+        match self.peek() {
+            Some(c) => eyre!("{message} at '{c}' (position {})", self.pos + 1),
+            None => eyre!("{message} at end of algorithm"),
+        }
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            None
+        } else {
+            self.chars[start..self.pos]
+                .iter()
+                .collect::<String>()
+                .parse()
+                .ok()
+        }
+    }
+
+    /// Parses a count and a prime.
+    /// The count is taken modulo the ray order, so `R5` turns like `R` instead of spinning.
+    fn turn_suffix(&mut self) -> eyre::Result<(i8, bool)> {
+        let (count, prime) = self.suffix()?;
+        Ok((count % Ray::order(), prime))
+    }
+
+    /// Parses a repetition count and a prime.
+    fn suffix(&mut self) -> eyre::Result<(i8, bool)> {
+        let count = match self.number() {
+            Some(count) => i8::try_from(count).map_err(|_| self.error("count is too large"))?,
+            None => 1,
+        };
+        Ok((count, self.eat('\'')))
+    }
+
+    /// Fails if expanding to `length` twists would be too many.
+    fn check_length(&self, length: usize) -> eyre::Result<()> {
+        if length > MAX_TWISTS {
+            return Err(self.error(&format!("algorithm is longer than {MAX_TWISTS} twists")));
+        }
+        Ok(())
+    }
+
+    /// Parses a layer range like `2` or `2-5`, 1-indexed, as its first and last layer.
+    fn range(&mut self) -> eyre::Result<Option<(usize, usize)>> {
+        let Some(start) = self.number() else {
+            return Ok(None);
+        };
+        let end = if self.eat('-') {
+            self.number().ok_or_else(|| self.error("expected layer"))?
+        } else {
+            start
+        };
+        if start == 0 || end < start {
+            return Err(self.error("invalid layer range"));
+        }
+        Ok(Some((start, end)))
+    }
+
+    fn layers(&mut self) -> eyre::Result<Option<Vec<(usize, usize)>>> {
+        if self.eat('{') {
+            let mut layers = vec![];
+            loop {
+                self.skip_whitespace();
+                if self.eat('}') {
+                    break;
+                }
+                let range = self.range()?.ok_or_else(|| self.error("expected layer"))?;
+                layers.push(range);
+                self.skip_whitespace();
+                if !self.eat(',') {
+                    self.expect('}')?;
+                    break;
+                }
+            }
+            Ok(Some(layers))
+        } else {
+            Ok(self.range()?.map(|range| vec![range]))
+        }
+    }
+
+    fn ray(&mut self) -> Option<Ray> {
+        let rest: String = self.chars[self.pos..].iter().collect();
+        let (name, ray) = self.names.iter().find(|(name, _)| rest.starts_with(name))?;
+        self.pos += name.chars().count();
+        Some(*ray)
+    }
+
+    fn rotation(&mut self) -> Option<Ray> {
+        if self.eat('@') {
+            return self.ray();
+        }
+        let rest: String = self.chars[self.pos..].iter().collect();
+        let (name, ray) = Ray::ROTATION_NAMES
+            .iter()
+            .find(|(name, _)| rest.starts_with(name))?;
+        self.pos += name.chars().count();
+        Some(*ray)
+    }
+
+    fn move_(&mut self) -> eyre::Result<Twist<Ray>> {
+        let start = self.pos;
+        if let Some(ray) = self.rotation() {
+            let (count, prime) = self.turn_suffix()?;
+            let order = count * ray.clockwise() * if prime { -1 } else { 1 };
+            return Ok(((ray, order), self.puzzle.grips.clone()));
+        } else if self.pos != start {
+            return Err(self.error("expected face"));
+        }
+
+        let ranges = self.layers()?.unwrap_or_else(|| vec![(1, 1)]);
+        let ray = self.ray().ok_or_else(|| self.error("expected face"))?;
+        let (count, prime) = self.turn_suffix()?;
+
+        let face_grips = self.puzzle.grips_from_face(ray);
+        // checked before collecting the layers, so a typo like 1-99999999R fails quickly
+        if let Some(&(_, end)) = ranges.iter().find(|(_, end)| *end > face_grips.len()) {
+            return Err(eyre!("{ray} has no layer {end}"));
+        }
+        let grips = ranges
+            .into_iter()
+            .flat_map(|(start, end)| start..=end)
+            .unique()
+            .map(|layer| face_grips[layer - 1])
+            .collect();
+
+        let order = count * ray.clockwise() * if prime { -1 } else { 1 };
+        Ok(((ray, order), grips))
+    }
+
+    fn item(&mut self) -> eyre::Result<Vec<Twist<Ray>>> {
+        if self.eat('(') {
+            let inner = self.sequence()?;
+            self.expect(')')?;
+            let (count, prime) = self.suffix()?;
+            self.check_length(inner.len() * count as usize)?;
+            Ok(repeat(inner, count, prime))
+        } else if self.eat('[') {
+            let first = self.sequence()?;
+            self.skip_whitespace();
+            let inner = if self.eat(',') {
+                let second = self.sequence()?;
+                self.check_length(2 * (first.len() + second.len()))?;
+                [
+                    first.clone(),
+                    second.clone(),
+                    invert(&first),
+                    invert(&second),
+                ]
+                .concat()
+            } else if self.eat(':') {
+                let second = self.sequence()?;
+                self.check_length(2 * first.len() + second.len())?;
+                [first.clone(), second, invert(&first)].concat()
+            } else {
+                return Err(self.error("expected ',' or ':'"));
+            };
+            self.expect(']')?;
+            let (count, prime) = self.suffix()?;
+            self.check_length(inner.len() * count as usize)?;
+            Ok(repeat(inner, count, prime))
+        } else {
+            Ok(vec![self.move_()?])
+        }
+    }
+
+    fn sequence(&mut self) -> eyre::Result<Vec<Twist<Ray>>> {
+        let mut twists = vec![];
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None | Some(')' | ']' | ',' | ':') => return Ok(twists),
+                _ => {
+                    let item = self.item()?;
+                    self.check_length(twists.len() + item.len())?;
+                    twists.extend(item);
+                }
+            }
+        }
+    }
+}
+
+/// Parses an algorithm into twists on the puzzle.
+pub fn parse<Ray: RaySystem>(puzzle: &Puzzle<Ray>, text: &str) -> eyre::Result<Vec<Twist<Ray>>> {
+    let names = enum_iter::<Ray>()
+        .map(|ray| (ray.name(), ray))
+        .sorted_by_key(|(name, _)| std::cmp::Reverse(name.len()))
+        .collect();
+    let mut parser = Parser {
+        chars: text.chars().collect(),
+        pos: 0,
+        puzzle,
+        names,
+    };
+    let twists = parser.sequence()?;
+    if parser.peek().is_some() {
+        return Err(parser.error("unexpected character"));
+    }
+    Ok(twists)
+}
+
+fn format_layers(layers: &[usize]) -> String {
+    let ranges: Vec<String> = layers
+        .iter()
+        .map(|&l| (l, l))
+        .coalesce(|(s1, e1), (s2, e2)| {
+            if e1 + 1 == s2 {
+                Ok((s1, e2))
+            } else {
+                Err(((s1, e1), (s2, e2)))
+            }
+        })
+        .map(|(s, e)| {
+            if s == e {
+                s.to_string()
+            } else {
+                format!("{s}-{e}")
+            }
+        })
+        .collect();
+    match &ranges[..] {
+        [range] if range == "1" => "".to_string(),
+        [range] => range.clone(),
+        _ => format!("{{{}}}", ranges.join(",")),
+    }
+}
+
+/// Writes a single twist in notation.
+pub fn format_twist<Ray: RaySystem>(puzzle: &Puzzle<Ray>, twist: &Twist<Ray>) -> String {
+    let ((ray, order), grips) = twist;
+
+    if puzzle.is_rotation(grips) {
+        if let Some((name, named_ray)) = Ray::ROTATION_NAMES
+            .iter()
+            .find(|(_, r)| r.get_axis() == ray.get_axis())
+        {
+            return format!("{}{}", name, format_order(*named_ray, *order));
+        }
+        return format!("@{}{}", ray.name(), format_order(*ray, *order));
+    }
+
+    let face_grips = puzzle.grips_from_face(*ray);
+    let layers: Vec<usize> = face_grips
+        .iter()
+        .positions(|grip| grips.contains(grip))
+        .map(|i| i + 1)
+        .collect();
+
+    format!(
+        "{}{}{}",
+        format_layers(&layers),
+        ray.name(),
+        format_order(*ray, *order)
+    )
+}
+
+/// Writes the count and prime of a turn as seen from the face.
+fn format_order<Ray: RaySystem>(ray: Ray, order: i8) -> String {
+    let turns = (order * ray.clockwise()).rem_euclid(Ray::order());
+    if turns == 0 {
+        "0".to_string()
+    } else if turns == 1 {
+        "".to_string()
+    } else if Ray::order() - turns == 1 {
+        "'".to_string()
+    } else if turns * 2 <= Ray::order() {
+        turns.to_string()
+    } else {
+        format!("{}'", Ray::order() - turns)
+    }
+}
+
+/// Writes a twist sequence in notation.
+pub fn format_twists<Ray: RaySystem>(puzzle: &Puzzle<Ray>, twists: &[Twist<Ray>]) -> String {
+    twists
+        .iter()
+        .map(|twist| format_twist(puzzle, twist))
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzle::cube::name::*;
+    use crate::puzzle::octa::OctaRay;
+
+    fn cube(n: i8) -> Puzzle<crate::puzzle::cube::CubeRay> {
+        Puzzle::make_nnn(n)
+    }
+
+    #[test]
+    fn parse_moves() {
+        let puzzle = cube(4);
+        let twists = parse(&puzzle, "R U' 2F2 2-3L'").unwrap();
+        assert_eq!(
+            twists,
+            vec![
+                ((R, 1), vec![[3, -3]]),
+                ((U, -1), vec![[3, -3]]),
+                ((F, -2), vec![[-1, 1]]),
+                ((L, 1), vec![[-1, 1], [1, -1]]),
+            ]
+        );
+    }
+
+    #[test]
+    fn commutator_and_conjugate() {
+        let puzzle = cube(3);
+        let comm = parse(&puzzle, "[R, U]").unwrap();
+        assert_eq!(comm, parse(&puzzle, "R U R' U'").unwrap());
+        let conj = parse(&puzzle, "[F: (R U)2]").unwrap();
+        assert_eq!(conj, parse(&puzzle, "F R U R U F'").unwrap());
+        let inv = parse(&puzzle, "[R, U]'").unwrap();
+        assert_eq!(inv, parse(&puzzle, "U R U' R'").unwrap());
+    }
+
+    #[test]
+    fn six_sexy_solves() {
+        let mut puzzle = cube(3);
+        for ((ray, order), grips) in parse(&puzzle, "[R, U]6").unwrap() {
+            for grip in grips {
+                puzzle.twist((ray, order), &grip);
+            }
+        }
+        assert!(puzzle.is_solved());
+    }
+
+    #[test]
+    fn longest_names() {
+        let puzzle: Puzzle<OctaRay> = Puzzle::make_solved(vec![[-1, 1], [1, -1]]);
+        let twists = parse(&puzzle, "BU BR' U").unwrap();
+        let rays: Vec<_> = twists.iter().map(|((ray, _), _)| ray.name()).collect();
+        assert_eq!(rays, vec!["BU", "BR", "U"]);
+    }
+
+    #[test]
+    fn round_trip() {
+        let puzzle = cube(5);
+        let text = "R U' 2F2 2-3L' {1,3-4}D 5B";
+        assert_eq!(format_twists(&puzzle, &parse(&puzzle, text).unwrap()), text);
+    }
+
+    #[test]
+    fn rotations() {
+        let puzzle = cube(3);
+        let twists = parse(&puzzle, "x y' @F2").unwrap();
+        assert_eq!(twists[0], ((R, 1), puzzle.grips.clone()));
+        assert_eq!(twists[1], ((U, -1), puzzle.grips.clone()));
+        assert_eq!(format_twists(&puzzle, &twists), "x y' z2");
+        assert_eq!(
+            format_twists(&puzzle, &parse(&puzzle, "@L @D2 1-3B").unwrap()),
+            "x' y2 z'"
+        );
+
+        let puzzle: Puzzle<OctaRay> = Puzzle::make_solved(vec![[-1, 1], [1, -1]]);
+        let twists = parse(&puzzle, "@BU'").unwrap();
+        assert_eq!(format_twists(&puzzle, &twists), "@BU'");
+    }
+
+    #[test]
+    fn errors() {
+        let puzzle = cube(3);
+        assert!(parse(&puzzle, "R X").is_err());
+        assert!(parse(&puzzle, "4R").is_err());
+        assert!(parse(&puzzle, "[R U]").is_err());
+        assert!(parse(&puzzle, "(R U").is_err());
+        assert!(parse(&puzzle, "1-99999999999R").is_err());
+        assert!(parse(&puzzle, "{1,4}R").is_err());
+        assert!(parse(&puzzle, "((((R)127)127)127)127").is_err());
+        assert!(parse(
+            &puzzle,
+            "[[[[[[[[[[[[[[R, U], U], U], U], U], U], U], U], U], U], U], U], U], U]"
+        )
+        .is_err());
+        assert_eq!(parse(&puzzle, "(R U)100").unwrap().len(), 200);
+    }
+
+    #[test]
+    fn large_counts() {
+        let puzzle = cube(3);
+        assert_eq!(
+            parse(&puzzle, "R127").unwrap(),
+            parse(&puzzle, "R3").unwrap()
+        );
+        assert_eq!(
+            parse(&puzzle, "x5'").unwrap(),
+            parse(&puzzle, "x'").unwrap()
+        );
+        assert!(parse(&puzzle, "R128").is_err());
+    }
+
+    #[test]
+    fn layer_lists() {
+        assert_eq!(parse_layer_list("2-5").unwrap(), vec![2, 3, 4, 5]);
+        assert_eq!(parse_layer_list(" 1, 3-4,12 ").unwrap(), vec![1, 3, 4, 12]);
+        assert_eq!(parse_layer_list("2, 1-3").unwrap(), vec![2, 1, 3]);
+        assert_eq!(parse_layer_list("").unwrap(), Vec::<usize>::new());
+        assert!(parse_layer_list("0").is_err());
+        assert!(parse_layer_list("5-2").is_err());
+        assert!(parse_layer_list("R").is_err());
+    }
+}
@@ -1,8 +1,8 @@
 use enum_map::Enum;
 use std::fmt;
 
-use crate::puzzle::common::RaySystem;
 pub use crate::puzzle::common::{Basis, BasisDiff, Sign};
+use crate::puzzle::common::{RaySystem, AXIS_RAYS};
 
 #[derive(Debug, Enum, Clone, Copy, PartialEq, Eq)]
 pub struct OctaRay(pub Sign, pub Sign, pub Sign);
@@ -18,11 +18,11 @@ impl OctaRay {
 }
 
 impl RaySystem for OctaRay {
-    fn get_axis(&self) -> Vec<Self> {
+    fn get_axis(&self) -> [Self; AXIS_RAYS] {
         if self.tet_sign() == Sign::Pos {
-            vec![*self, self.opposite()]
+            [*self, self.opposite()]
         } else {
-            vec![self.opposite(), *self]
+            [self.opposite(), *self]
         }
     }
 
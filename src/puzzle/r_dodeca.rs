@@ -2,16 +2,16 @@ use crate::puzzle::cube::CubeRay;
 use enum_map::Enum;
 use std::fmt;
 
-use crate::puzzle::common::RaySystem;
 pub use crate::puzzle::common::{Basis, BasisDiff, Sign};
+use crate::puzzle::common::{RaySystem, AXIS_RAYS};
 
 /// RDodecaRay(a, ±₁, ±₂) => ±₁a⁺ + ±₂a⁺⁺
 #[derive(Debug, Enum, Clone, Copy, PartialEq, Eq)]
 pub struct RDodecaRay(pub Basis, pub Sign, pub Sign);
 
 impl RaySystem for RDodecaRay {
-    fn get_axis(&self) -> Vec<Self> {
-        vec![
+    fn get_axis(&self) -> [Self; AXIS_RAYS] {
+        [
             Self(self.0, Sign::Pos, self.1 * self.2),
             Self(self.0, Sign::Neg, -self.1 * self.2),
         ]
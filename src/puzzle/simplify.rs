@@ -0,0 +1,143 @@
+use crate::puzzle::common::*;
+use itertools::Itertools;
+
+/// Puts an order in the range (-order/2, order/2].
+pub fn normalize_order<Ray: RaySystem>(order: i8) -> i8 {
+    let order = order.rem_euclid(Ray::order());
+    if order * 2 > Ray::order() {
+        order - Ray::order()
+    } else {
+        order
+    }
+}
+
+/// Merges a list of twists on the same axis.
+/// Twists on the same axis commute, so only the total order on each grip matters.
+/// Whole-puzzle rotations are merged apart from the other twists and kept last,
+/// so that a rotation next to a turn does not become two slice turns.
+fn merge_axis<Ray: RaySystem>(puzzle: &Puzzle<Ray>, block: &[Twist<Ray>]) -> Vec<Twist<Ray>> {
+    let (rotations, turns): (Vec<_>, Vec<_>) = block
+        .iter()
+        .partition(|(_, grips)| puzzle.is_rotation(grips));
+
+    // (grip, total order, ray it was first turned with), in order of first appearance
+    let mut grip_orders: Vec<(Grip, i8, Ray)> = vec![];
+    for ((ray, order), grips) in turns {
+        for grip in grips {
+            match grip_orders.iter_mut().find(|(g, _, _)| g == grip) {
+                Some((_, total, _)) => {
+                    // both are below the ray order, so this can't overflow
+                    *total = (*total + order.rem_euclid(Ray::order())).rem_euclid(Ray::order())
+                }
+                None => grip_orders.push((*grip, order.rem_euclid(Ray::order()), *ray)),
+            }
+        }
+    }
+
+    // grips that end up turned by the same amount can be turned together
+    let mut merged: Vec<Twist<Ray>> = grip_orders
+        .iter()
+        .filter(|(_, order, _)| *order != 0)
+        .map(|(_, order, _)| *order)
+        .unique()
+        .map(|order| {
+            let mut group = grip_orders.iter().filter(|(_, o, _)| *o == order);
+            let (_, _, ray) = group.clone().next().expect("order came from a grip");
+            let grips = group.by_ref().map(|(grip, _, _)| *grip).collect();
+            ((*ray, normalize_order::<Ray>(order)), grips)
+        })
+        .collect();
+
+    if let Some(((ray, _), _)) = rotations.first() {
+        let total = rotations.iter().fold(0, |total, ((_, order), _)| {
+            (total + order.rem_euclid(Ray::order())).rem_euclid(Ray::order())
+        });
+        if total != 0 {
+            merged.push(((*ray, normalize_order::<Ray>(total)), puzzle.grips.clone()));
+        }
+    }
+    merged
+}
+
+/// Simplifies a twist sequence by merging consecutive twists on the same axis,
+/// cancelling twists that undo each other and dropping twists that do nothing.
+pub fn simplify<Ray: RaySystem>(puzzle: &Puzzle<Ray>, twists: &[Twist<Ray>]) -> Vec<Twist<Ray>> {
+    let mut simplified: Vec<Twist<Ray>> = vec![];
+    for twist in twists {
+        let axis = twist.0 .0.get_axis();
+        // the twists at the end on the same axis as this one
+        let start = simplified
+            .iter()
+            .rposition(|((ray, _), _)| ray.get_axis() != axis)
+            .map_or(0, |i| i + 1);
+        let mut block = simplified.split_off(start);
+        block.push(twist.clone());
+        simplified.extend(merge_axis(puzzle, &block));
+    }
+    simplified
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzle::cube::name::R;
+    use crate::puzzle::cube::CubeRay;
+    use crate::puzzle::notation::{format_twists, parse};
+
+    fn cube(n: i8) -> Puzzle<CubeRay> {
+        Puzzle::make_nnn(n)
+    }
+
+    fn simplified(puzzle: &Puzzle<CubeRay>, text: &str) -> String {
+        format_twists(puzzle, &simplify(puzzle, &parse(puzzle, text).unwrap()))
+    }
+
+    #[test]
+    fn cancels() {
+        let puzzle = cube(3);
+        assert_eq!(simplified(&puzzle, "R U U' R'"), "");
+        assert_eq!(simplified(&puzzle, "R U2 U2 R"), "R2");
+        assert_eq!(simplified(&puzzle, "F R R R R B"), "F B");
+    }
+
+    #[test]
+    fn merges() {
+        let puzzle = cube(4);
+        assert_eq!(simplified(&puzzle, "R R"), "R2");
+        assert_eq!(simplified(&puzzle, "R R R"), "R'");
+        assert_eq!(simplified(&puzzle, "1-2R R'"), "2R");
+        assert_eq!(simplified(&puzzle, "R U 2R'"), "R U 2R'");
+        assert_eq!(simplified(&puzzle, "R L L'"), "R");
+        // large orders, as a log could have
+        let twists = [((R, 3), vec![[3, -3]]), ((R, 127), vec![[3, -3]])];
+        assert_eq!(format_twists(&puzzle, &simplify(&puzzle, &twists)), "R2");
+    }
+
+    #[test]
+    fn rotations() {
+        let puzzle = cube(3);
+        assert_eq!(simplified(&puzzle, "R x"), "R x");
+        assert_eq!(simplified(&puzzle, "x R x'"), "R");
+        assert_eq!(simplified(&puzzle, "R x L' x"), "{1,3}R x2");
+        assert_eq!(simplified(&puzzle, "x x'"), "");
+    }
+
+    #[test]
+    fn keeps_position() {
+        let puzzle = cube(4);
+        let twists = parse(&puzzle, "R L' 2R U U x 2L' R' [F, 2D] 3B2 y 3F2").unwrap();
+        let mut original = cube(4);
+        let mut simple = cube(4);
+        for ((ray, order), grips) in &twists {
+            for grip in grips {
+                original.twist((*ray, *order), grip);
+            }
+        }
+        for ((ray, order), grips) in simplify(&puzzle, &twists) {
+            for grip in grips {
+                simple.twist((ray, order), &grip);
+            }
+        }
+        assert_eq!(original.orientations(), simple.orientations());
+    }
+}
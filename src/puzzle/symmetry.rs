@@ -0,0 +1,230 @@
+use crate::puzzle::common::*;
+use crate::util::{enum_iter, enum_map_clone};
+use enum_map::EnumMap;
+use itertools::Itertools;
+use std::collections::VecDeque;
+
+/// A symmetry of the ray system, stored as the ray each ray is sent to.
+#[derive(Debug)]
+pub struct Symmetry<Ray: RaySystem>(pub EnumMap<Ray, Ray>);
+
+impl<Ray: RaySystem> Clone for Symmetry<Ray> {
+    fn clone(&self) -> Self {
+        Self(enum_map_clone(&self.0))
+    }
+}
+
+impl<Ray: RaySystem> Symmetry<Ray> {
+    pub fn identity() -> Self {
+        Self(EnumMap::from_fn(|ray| ray))
+    }
+
+    /// The symmetry that turns the whole puzzle.
+    pub fn from_turn(turn: (Ray, i8)) -> Self {
+        Self(EnumMap::from_fn(|ray: Ray| ray.turn(turn)))
+    }
+
+    /// Every rotation of the ray system, starting with the identity.
+    /// These are found by walking along `RaySystem::CYCLE`.
+    pub fn rotations() -> Vec<Self> {
+        let mut rotations = vec![Self::identity()];
+        for &turn in Ray::CYCLE {
+            let last = rotations.last().expect("identity is always there");
+            rotations.push(last.then(&Self::from_turn(turn)));
+        }
+        rotations
+    }
+
+    /// Applies self, then other.
+    pub fn then(&self, other: &Self) -> Self {
+        Self(EnumMap::from_fn(|ray| other.0[self.0[ray]]))
+    }
+
+    pub fn apply_ray(&self, ray: Ray) -> Ray {
+        self.0[ray]
+    }
+
+    /// Moves a twist to where the symmetry sends it.
+    pub fn apply_twist(&self, twist: &Twist<Ray>) -> Twist<Ray> {
+        let ((ray, order), grips) = twist;
+        let new_ray = self.apply_ray(*ray);
+
+        // a single turn is sent to either a single turn or its inverse around the new axis
+        let unit = [1, -1]
+            .into_iter()
+            .find(|&unit| {
+                enum_iter::<Ray>().all(|r| {
+                    self.apply_ray(r.turn((*ray, 1))) == self.apply_ray(r).turn((new_ray, unit))
+                })
+            })
+            .expect("symmetries should preserve turns");
+
+        // new_grip[j] is the layer of the ray sent to new_axis[j]
+        let axis = ray.get_axis();
+        let new_axis = new_ray.get_axis();
+        let grip_indices = new_axis.map(|new_r| {
+            axis.iter()
+                .position(|&r| self.apply_ray(r) == new_r)
+                .expect("symmetries should preserve axes")
+        });
+        let new_grips = grips
+            .iter()
+            .map(|grip| grip_indices.map(|i| grip[i]))
+            .collect();
+
+        ((new_ray, order * unit), new_grips)
+    }
+
+    pub fn apply_twists(&self, twists: &[Twist<Ray>]) -> Vec<Twist<Ray>> {
+        twists.iter().map(|twist| self.apply_twist(twist)).collect()
+    }
+
+    /// A short description of the symmetry, by where it sends the first axis heads.
+    pub fn name(&self) -> String {
+        Ray::AXIS_HEADS
+            .iter()
+            .take(2)
+            .map(|&ray| format!("{ray}→{}", self.apply_ray(ray)))
+            .join(", ")
+    }
+}
+
+/// The type of a piece with these solved layers, like corner or edge.
+/// Two pieces have the same type if a rotation sends one's position to the other's,
+/// and the type is the smallest layers (in ray order) among the rotated positions.
+pub fn piece_type<Ray: RaySystem>(layers: &EnumMap<Ray, i8>) -> Vec<i8> {
+    Symmetry::<Ray>::rotations()
+        .iter()
+        .map(|rotation| {
+            let mut rotated = vec![0; Ray::LENGTH];
+            for (ray, &layer) in layers {
+                rotated[Ray::into_usize(rotation.apply_ray(ray))] = layer;
+            }
+            rotated
+        })
+        .min()
+        .expect("identity is always a rotation")
+}
+
+/// Finds the shortest sequence of whole-puzzle turns that moves
+/// whatever is in the direction of `from` to the direction of `to`.
+/// Returns None if no rotation does this, like for opposite faces of the FTO.
+pub fn turns_moving<Ray: RaySystem>(from: Ray, to: Ray) -> Option<Vec<(Ray, i8)>> {
+    let generators: Vec<(Ray, i8)> = Ray::AXIS_HEADS
+        .iter()
+        .flat_map(|&axis| (1..Ray::order()).map(move |order| (axis, order)))
+        .collect();
+
+    // the search goes backwards from `to`: each state is the direction whose contents
+    // end up at `to` after doing the turns
+    let mut queue = VecDeque::from([(to, vec![])]);
+    let mut seen = vec![to];
+    while let Some((source, turns)) = queue.pop_front() {
+        if source == from {
+            return Some(turns);
+        }
+        for &turn in &generators {
+            let next = source.turn(turn);
+            if !seen.contains(&next) {
+                seen.push(next);
+                queue.push_back((next, [vec![turn], turns.clone()].concat()));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzle::cube::{name::*, CubeRay};
+    use crate::puzzle::dodeca::DodecaRay;
+    use crate::puzzle::octa::OctaRay;
+    use crate::puzzle::r_dodeca::RDodecaRay;
+
+    fn rotations_distinct<Ray: RaySystem>(count: usize) {
+        let rotations = Symmetry::<Ray>::rotations();
+        assert_eq!(rotations.len(), count);
+        for pair in rotations.iter().combinations(2) {
+            assert!(pair[0].0 != pair[1].0, "rotations are not distinct");
+        }
+    }
+
+    #[test]
+    fn rotation_groups() {
+        rotations_distinct::<CubeRay>(24);
+        rotations_distinct::<OctaRay>(12);
+        rotations_distinct::<DodecaRay>(60);
+        rotations_distinct::<RDodecaRay>(24);
+    }
+
+    fn turns_move_everything<Ray: RaySystem>() {
+        let mut puzzle: Puzzle<Ray> = Puzzle::make_solved(vec![[0; 2]]);
+        for from in enum_iter::<Ray>() {
+            for to in enum_iter::<Ray>() {
+                puzzle.set_orientations(&[EnumMap::from_fn(|ray| ray)]);
+                let Some(turns) = turns_moving(from, to) else {
+                    // the rotations have to reach the rays in the same orbit
+                    assert!(Symmetry::<Ray>::rotations()
+                        .iter()
+                        .all(|rotation| rotation.apply_ray(from) != to));
+                    continue;
+                };
+                for turn in turns {
+                    puzzle.twist(turn, &[0, 0]);
+                }
+                assert_eq!(puzzle.pieces[0].orientation[to], from);
+            }
+        }
+    }
+
+    #[test]
+    fn reorient() {
+        turns_move_everything::<CubeRay>();
+        turns_move_everything::<OctaRay>();
+        turns_move_everything::<DodecaRay>();
+        turns_move_everything::<RDodecaRay>();
+    }
+
+    #[test]
+    fn piece_types() {
+        let type_count = |n: i8| {
+            let puzzle: Puzzle<CubeRay> = Puzzle::make_nnn(n);
+            puzzle
+                .pieces
+                .iter()
+                .map(|piece| piece_type(&piece.layers))
+                .unique()
+                .count()
+        };
+        // corners, edges, centers and the core
+        assert_eq!(type_count(3), 4);
+        // wings form a single type since the rotations swap left and right wings
+        assert_eq!(type_count(4), 4);
+        // corners, wings, midges, x-centers, t-centers, centers
+        // and the four types of inner pieces
+        assert_eq!(type_count(5), 10);
+    }
+
+    #[test]
+    fn rotate_sexy_move() {
+        let y = Symmetry::from_turn((U, 1));
+        let mut puzzle: Puzzle<CubeRay> = Puzzle::make_solved(vec![[-1, 1], [1, -1]]);
+        let sexy = vec![
+            ((R, -1), vec![[1, -1]]),
+            ((U, -1), vec![[1, -1]]),
+            ((R, 1), vec![[1, -1]]),
+            ((U, 1), vec![[1, -1]]),
+        ];
+        let rotated = y.apply_twists(&sexy);
+        assert_eq!(rotated[0].0 .0, B);
+        for _ in 0..6 {
+            for ((ray, order), grips) in &rotated {
+                for grip in grips {
+                    puzzle.twist((*ray, *order), grip);
+                }
+            }
+        }
+        assert!(puzzle.is_solved());
+    }
+}
@@ -1,16 +1,20 @@
 use crate::puzzle::common::*;
+use crate::puzzle::symmetry::Symmetry;
 use crate::ANIMATION_INIT_V;
 use enum_map::Enum;
 use enum_map::EnumMap;
 use std::cmp;
-use std::collections::HashMap;
+use std::iter;
 
 use crate::preferences::Preferences;
+use crate::puzzle::filter::PieceFilter;
 use crate::util::{color, Mat4, Vec3};
 use std::f32::consts::PI;
 use three_d::*;
 
 const DEFAULT_HEIGHT: Deg<f32> = Deg(20.0);
+/// How bright stickers facing away from the light are, with lighting on
+const AMBIENT_LIGHT: f32 = 0.55;
 
 #[derive(Debug, Enum, Clone, Copy, PartialEq, Eq)]
 pub enum BinaryConjugate {
@@ -137,12 +141,47 @@ pub struct StickerOptions {
 }
 
 impl<Ray: RaySystem> Puzzle<Ray> {
-    pub fn piece_by_ind(&self, piece_ind: StickerInd, permutation: &[usize]) -> &Piece<Ray> {
+    /// The index in `pieces` of the piece the sticker is on.
+    pub fn piece_index_by_ind(&self, piece_ind: StickerInd) -> usize {
+        let slot = self
+            .position_slot(piece_ind.position())
+            .expect("the puzzle should have the positions of its stickers");
         match piece_ind {
-            StickerInd::Normal(ind) => &self.pieces[permutation[ind]],
-            StickerInd::Core(ind) => &self.pieces[ind],
+            StickerInd::Normal(_) => self.permutation()[slot],
+            StickerInd::Core(_) => slot,
         }
     }
+
+    pub fn piece_by_ind(&self, piece_ind: StickerInd) -> &Piece<Ray> {
+        &self.pieces[self.piece_index_by_ind(piece_ind)]
+    }
+}
+
+impl<Ray: ConcreteRaySystem> Symmetry<Ray> {
+    /// The symmetry that does the concrete turn to the ray vectors,
+    /// or None if the turn does not send rays to rays.
+    pub fn from_concrete(turn: ConcreteTurn, conjugate: Ray::Conjugate) -> Option<Self> {
+        const EPSILON: f32 = 1e-4;
+        let mat = turn.to_transform();
+        let images: Vec<Ray> = crate::util::enum_iter::<Ray>()
+            .map(|ray| {
+                let image = (mat * ray.ray_to_vec(conjugate).extend(0.0)).truncate();
+                crate::util::enum_iter::<Ray>()
+                    .find(|r| r.ray_to_vec(conjugate).distance(image) < EPSILON)
+            })
+            .collect::<Option<_>>()?;
+        Some(Self(EnumMap::from_fn(|ray| images[Ray::into_usize(ray)])))
+    }
+
+    /// Reflection across the plane perpendicular to the x axis.
+    /// Every ray system so far is symmetric under it.
+    pub fn mirror() -> Self {
+        let conjugate = crate::util::enum_iter::<Ray::Conjugate>()
+            .next()
+            .expect("there should be a conjugate");
+        Self::from_concrete(ConcreteTurn::Reflection(Vec3::unit_x()), conjugate)
+            .expect("ray system should be symmetric across the x axis")
+    }
 }
 
 /// The initial data which will be symmetry-expanded into a sticker.
@@ -178,6 +217,15 @@ pub enum StickerInd {
     Core(usize),
 }
 
+impl StickerInd {
+    /// The position index the sticker refers to.
+    pub fn position(&self) -> usize {
+        match *self {
+            Self::Normal(ind) | Self::Core(ind) => ind,
+        }
+    }
+}
+
 //#[derive(Debug)]
 pub struct Sticker<Ray>
 where
@@ -191,8 +239,29 @@ where
     pub color: Ray,
     /// The vertices of the polygon that makes up the sticker.
     pub vertices: Vec<Vec3>,
-    pub gm: Gm<Mesh, ColorMaterial>,
+    pub shape: StickerShape,
     pub animation: Option<StickerAnimation>,
+    /// How the sticker looked when it was last written to its viewport's mesh
+    pub drawn: Option<DrawnSticker>,
+}
+
+/// Everything drawn for one sticker, before animations.
+pub struct StickerShape {
+    /// The polygon of the colored part of the sticker
+    pub main: Vec<Vec3>,
+    /// The outer polygon followed by the inner polygon of the outline ring
+    pub outline: Option<Vec<Vec3>>,
+    /// The polygon of the plastic behind the sticker
+    pub internal_face: Option<Vec<Vec3>>,
+    pub outline_color: Srgba,
+}
+
+/// How a sticker is drawn in its viewport's mesh.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrawnSticker {
+    pub color: Srgba,
+    pub transformation: Mat4,
+    pub visible: bool,
 }
 
 /// Smoothly maps 0 to 0 and 1 to 1, with derivative ANIMATION_INIT_V at 0 and 1.
@@ -211,7 +280,22 @@ impl<Ray: ConcreteRaySystem> Sticker<Ray> {
             .reduce(f32::min)
     }
 
-    pub fn update_gm(&mut self, color: Srgba, elapsed_time: f32, animation_length: f32) {
+    /// The direction the sticker faces, before animations.
+    pub fn normal(&self) -> Vec3 {
+        (self.vertices[1] - self.vertices[0])
+            .cross(self.vertices[2] - self.vertices[0])
+            .normalize()
+    }
+
+    /// Updates the animation and color of the sticker,
+    /// and returns whether it looks different from when it was last written to its viewport's mesh.
+    pub fn update(
+        &mut self,
+        color: Srgba,
+        visible: bool,
+        elapsed_time: f32,
+        animation_length: f32,
+    ) -> bool {
         // can this section be written better
         let remove_animation;
         let sticker_mat;
@@ -235,16 +319,236 @@ impl<Ray: ConcreteRaySystem> Sticker<Ray> {
             sticker_mat = Mat4::identity();
         }
 
-        self.gm.material.color = color;
-        self.gm.set_transformation(sticker_mat);
-        self.gm.material.render_states.cull = if sticker_mat.determinant() > 0.0 {
-            Cull::Back
-        } else {
-            Cull::Front
+        let drawn = Some(DrawnSticker {
+            color,
+            transformation: sticker_mat,
+            visible,
+        });
+        let changed = drawn != self.drawn;
+        self.drawn = drawn;
+        changed
+    }
+}
+
+/// How many stickers are in each row of a `ViewportMesh`'s sticker data.
+/// Each takes five texels, and textures may be no wider than 2048.
+const STICKERS_PER_ROW: usize = 256;
+const TEXELS_PER_STICKER: usize = 5;
+/// Where the sticker shaders are kept in the context's program cache
+const STICKER_PROGRAM_KEY: &[u8] = b"laminated stickers";
+
+/// The stickers of a viewport as one mesh that is built once.
+/// Each sticker's color and animation are in a texture the shader reads,
+/// so a sticker that changes only rewrites its own texels, and lighting is a uniform.
+pub struct ViewportMesh {
+    context: Context,
+    positions: VertexBuffer,
+    normals: VertexBuffer,
+    stickers: VertexBuffer,
+    part_colors: VertexBuffer,
+    indices: ElementBuffer,
+    sticker_data: context::Texture,
+}
+
+impl ViewportMesh {
+    /// Builds the mesh of the stickers' shapes. Their colors and animations are
+    /// left for `write_stickers`.
+    pub fn new<Ray: ConcreteRaySystem>(context: &Context, stickers: &[Sticker<Ray>]) -> Self {
+        let mut positions = vec![];
+        let mut normals = vec![];
+        let mut sticker_inds = vec![];
+        let mut part_colors = vec![];
+        let mut indices = vec![];
+        for (i, sticker) in stickers.iter().enumerate() {
+            let shape = &sticker.shape;
+            // the colored part has no color of its own
+            let mut parts = vec![(
+                &shape.main,
+                polygon_inds(shape.main.len()),
+                Srgba::new(0, 0, 0, 0),
+            )];
+            if let Some(outline) = &shape.outline {
+                parts.push((outline, ring_inds(outline.len() / 2), shape.outline_color));
+            }
+            if let Some(internal_face) = &shape.internal_face {
+                let inds = polygon_inds(internal_face.len());
+                parts.push((internal_face, inds, shape.outline_color));
+            }
+            let normal = sticker.normal();
+            for (vertices, inds, color) in parts {
+                let start = positions.len() as u32;
+                positions.extend(vertices.iter().copied());
+                normals.extend(vertices.iter().map(|_| normal));
+                sticker_inds.extend(vertices.iter().map(|_| i as u32));
+                part_colors.extend(vertices.iter().map(|_| srgba_to_floats(color)));
+                indices.extend(inds.iter().flatten().map(|&ind| start + ind as u32));
+            }
+        }
+
+        let rows = stickers.len().div_ceil(STICKERS_PER_ROW).max(1);
+        let sticker_data = unsafe {
+            let texture = context
+                .create_texture()
+                .expect("should create the sticker texture");
+            context.bind_texture(context::TEXTURE_2D, Some(texture));
+            for parameter in [context::TEXTURE_MIN_FILTER, context::TEXTURE_MAG_FILTER] {
+                context.tex_parameter_i32(context::TEXTURE_2D, parameter, context::NEAREST as i32);
+            }
+            context.tex_image_2d(
+                context::TEXTURE_2D,
+                0,
+                context::RGBA32F as i32,
+                (STICKERS_PER_ROW * TEXELS_PER_STICKER) as i32,
+                rows as i32,
+                0,
+                context::RGBA,
+                context::FLOAT,
+                None,
+            );
+            context.bind_texture(context::TEXTURE_2D, None);
+            texture
         };
+
+        ViewportMesh {
+            context: context.clone(),
+            positions: VertexBuffer::new_with_data(context, &positions),
+            normals: VertexBuffer::new_with_data(context, &normals),
+            stickers: VertexBuffer::new_with_data(context, &sticker_inds),
+            part_colors: VertexBuffer::new_with_data(context, &part_colors),
+            indices: ElementBuffer::new_with_data(context, &indices),
+            sticker_data,
+        }
+    }
+
+    /// Writes how the stickers at these increasing indices were last updated.
+    /// Stickers next to each other in a row are written together.
+    pub fn write_stickers<Ray: ConcreteRaySystem>(
+        &self,
+        stickers: &[Sticker<Ray>],
+        changed: &[usize],
+    ) {
+        let mut start = 0;
+        while start < changed.len() {
+            let first = changed[start];
+            let mut end = start + 1;
+            while end < changed.len()
+                && changed[end] == changed[end - 1] + 1
+                && changed[end] / STICKERS_PER_ROW == first / STICKERS_PER_ROW
+            {
+                end += 1;
+            }
+
+            let data: Vec<f32> = changed[start..end]
+                .iter()
+                .flat_map(|&i| sticker_texels(&stickers[i]))
+                .collect();
+            unsafe {
+                self.context
+                    .bind_texture(context::TEXTURE_2D, Some(self.sticker_data));
+                self.context.tex_sub_image_2d(
+                    context::TEXTURE_2D,
+                    0,
+                    (first % STICKERS_PER_ROW * TEXELS_PER_STICKER) as i32,
+                    (first / STICKERS_PER_ROW) as i32,
+                    ((end - start) * TEXELS_PER_STICKER) as i32,
+                    1,
+                    context::RGBA,
+                    context::FLOAT,
+                    context::PixelUnpackData::Slice(to_bytes(&data)),
+                );
+                self.context.bind_texture(context::TEXTURE_2D, None);
+            }
+            start = end;
+        }
+    }
+
+    /// Draws the opaque stickers and then the translucent ones.
+    /// With a light direction, stickers get darker the more they face away from it.
+    /// Has to be called inside a render target's `write`.
+    pub fn render(&self, camera: &Camera, light: Option<Vec3>) {
+        let mut programs = self.context.programs.write().expect("not poisoned");
+        let program = programs
+            .entry(STICKER_PROGRAM_KEY.to_vec())
+            .or_insert_with(|| {
+                let fragment_shader = format!(
+                    "{}{}",
+                    ColorMapping::fragment_shader_source(),
+                    include_str!("shaders/stickers.frag")
+                );
+                Program::from_source(
+                    &self.context,
+                    include_str!("shaders/stickers.vert"),
+                    &fragment_shader,
+                )
+                .expect("the sticker shaders should compile")
+            });
+
+        program.use_uniform("viewProjection", camera.projection() * camera.view());
+        program.use_uniform("lightDirection", light.unwrap_or(Vec3::zero()));
+        let ambient = if light.is_some() { AMBIENT_LIGHT } else { 1.0 };
+        program.use_uniform("ambient", ambient);
+        camera.color_mapping.use_uniforms(program);
+        program.use_raw_texture("stickerData", context::TEXTURE_2D, self.sticker_data);
+        program.use_vertex_attribute("position", &self.positions);
+        program.use_vertex_attribute("normal", &self.normals);
+        program.use_vertex_attribute("sticker", &self.stickers);
+        program.use_vertex_attribute("partColor", &self.part_colors);
+
+        let opaque = RenderStates {
+            write_mask: WriteMask::COLOR_AND_DEPTH,
+            blend: Blend::Disabled,
+            ..Default::default()
+        };
+        let translucent = RenderStates {
+            write_mask: WriteMask::COLOR,
+            blend: Blend::TRANSPARENCY,
+            ..Default::default()
+        };
+        for (pass, render_states) in [(0, opaque), (1, translucent)] {
+            program.use_uniform("translucentPass", pass);
+            program.draw_elements(render_states, camera.viewport(), &self.indices);
+        }
+    }
+}
+
+impl Drop for ViewportMesh {
+    fn drop(&mut self) {
+        unsafe {
+            self.context.delete_texture(self.sticker_data);
+        }
     }
 }
 
+/// The sticker's color, with no alpha if it is hidden, then the columns of its transformation.
+fn sticker_texels<Ray: ConcreteRaySystem>(sticker: &Sticker<Ray>) -> [f32; 4 * TEXELS_PER_STICKER] {
+    let drawn = sticker
+        .drawn
+        .expect("stickers should be updated before they are written");
+    let mut color = drawn.color;
+    if !drawn.visible {
+        color.a = 0;
+    }
+    let color = srgba_to_floats(color);
+    let mat: &[[f32; 4]; 4] = drawn.transformation.as_ref();
+    let mut texels = [0.0; 4 * TEXELS_PER_STICKER];
+    for (texel, values) in texels
+        .chunks_mut(4)
+        .zip(iter::once(&color).chain(mat.iter()))
+    {
+        texel.copy_from_slice(values);
+    }
+    texels
+}
+
+/// The color's sRGB channels from 0 to 1. The shader turns them linear.
+fn srgba_to_floats(color: Srgba) -> [f32; 4] {
+    [color.r, color.g, color.b, color.a].map(|c| c as f32 / 255.0)
+}
+
+fn to_bytes(data: &[f32]) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, std::mem::size_of_val(data)) }
+}
+
 fn ray_triangle_intersect(position: Vec3, direction: Vec3, verts: &[Vec3]) -> Option<f32> {
     // https://en.wikipedia.org/wiki/M%C3%B6ller%E2%80%93Trumbore_intersection_algorithm
     const EPSILON: f32 = 0.0000001;
@@ -297,14 +601,100 @@ impl AbstractViewport {
     }
 }
 
+/// Vectors are saved as arrays, since cgmath does not implement serde here.
+mod vec3_serde {
+    use crate::util::Vec3;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(vec: &Vec3, serializer: S) -> Result<S::Ok, S::Error> {
+        AsRef::<[f32; 3]>::as_ref(vec).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec3, D::Error> {
+        <[f32; 3]>::deserialize(deserializer).map(Vec3::from)
+    }
+}
+
+fn default_zoom() -> f32 {
+    1.0
+}
+
 /// A three_d::Camera without the viewport
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct CameraFacing {
+    #[serde(with = "vec3_serde")]
     pub position: Vec3,
+    #[serde(with = "vec3_serde")]
     pub target: Vec3,
+    #[serde(with = "vec3_serde")]
     pub up: Vec3,
+    /// How much bigger the puzzle looks than with the default camera
+    #[serde(default = "default_zoom")]
+    pub zoom: f32,
+    #[serde(default)]
+    pub orthographic: bool,
+}
+
+impl Default for CameraFacing {
+    fn default() -> Self {
+        CameraFacing {
+            position: Vec3::new(5.0, -10.0, 4.0),
+            target: Vec3::new(0.0, 0.0, 0.0),
+            up: Vec3::new(0.0, 0.0, 1.0),
+            zoom: default_zoom(),
+            orthographic: false,
+        }
+    }
 }
 
 impl CameraFacing {
+    /// Goes back to the default camera, keeping the projection.
+    pub fn reset(&mut self) {
+        *self = CameraFacing {
+            orthographic: self.orthographic,
+            ..Default::default()
+        };
+    }
+
+    /// Zooms in for positive amounts and out for negative ones.
+    pub fn zoom_by(&mut self, amount: f32) {
+        self.zoom = (self.zoom * (amount * crate::ZOOM_SPEED).exp())
+            .clamp(crate::MIN_ZOOM, crate::MAX_ZOOM);
+    }
+
+    /// Moves the camera to look straight at the ray, keeping the distance and zoom.
+    pub fn look_at_ray<Ray: ConcreteRaySystem>(&mut self, ray: Ray, conjugate: Ray::Conjugate) {
+        let direction = ray.ray_to_vec(conjugate);
+        let distance = (self.position - self.target).magnitude();
+        let screen_up = self.screen_up();
+        // if the ray is already up on the screen, the side that faced the camera goes up
+        let up = if screen_up.cross(direction).magnitude() > 0.1 {
+            screen_up
+        } else {
+            (self.position - self.target).normalize()
+        };
+        self.position = self.target + direction * distance;
+        self.up = up;
+    }
+
+    /// The direction that points up on the screen.
+    pub fn screen_up(&self) -> Vec3 {
+        let pointing = (self.target - self.position).normalize();
+        (self.up - self.up.dot(pointing) * pointing).normalize()
+    }
+
+    /// The ray that points most nearly up on the screen.
+    pub fn top_ray<Ray: ConcreteRaySystem>(&self, conjugate: Ray::Conjugate) -> Ray {
+        let up = self.screen_up();
+        crate::util::enum_iter::<Ray>()
+            .max_by(|r1, r2| {
+                let d1 = r1.ray_to_vec(conjugate).dot(up);
+                let d2 = r2.ray_to_vec(conjugate).dot(up);
+                d1.partial_cmp(&d2).expect("not nan")
+            })
+            .expect("ray system should not be empty")
+    }
+
     pub fn orbit(&mut self, (dx, dy): (f32, f32)) {
         // (dx, dy) will never both be zero
         let pointing = -1.0 * self.position;
@@ -318,6 +708,7 @@ impl CameraFacing {
             position: mat * (-1.0 * pointing),
             target: mat * self.target,
             up: mat * (-1.0 * local_y_axis),
+            ..*self
         };
         /*
         // this has a weird bug where it slows down the more you rotate
@@ -337,7 +728,31 @@ where
     pub viewport: Viewport,
     pub conjugate: Ray::Conjugate,
     pub stickers: Vec<Sticker<Ray>>,
-    pub key_layers: Vec<HashMap<Key, Vec<i8>>>,
+    pub key_layers: Vec<Vec<Grip>>,
+    /// None until it is first built
+    pub mesh: Option<ViewportMesh>,
+    /// For each position slot of the puzzle, the indices in `stickers` of the normal stickers there.
+    /// Empty until `index_stickers`.
+    pub slot_stickers: Vec<Vec<usize>>,
+    /// For each piece of the puzzle, the indices in `stickers` of its core stickers
+    pub core_stickers: Vec<Vec<usize>>,
+    /// What colored the stickers when they were last drawn, or None to draw them all again
+    pub look: Option<ViewportLook>,
+    /// The layers that were highlighted when the stickers were last drawn
+    pub hovered_layers: Option<(Ray, Vec<Grip>)>,
+    /// The stickers that may look different from when they were last drawn
+    pub dirty: Vec<usize>,
+}
+
+/// Everything besides the piece on it and the highlighted layers that decides a sticker's color.
+/// When any of it changes, every sticker is drawn again.
+#[derive(Clone, PartialEq)]
+pub struct ViewportLook {
+    /// The colors of the faces, in enum order
+    pub colors: Vec<color::Color>,
+    pub colors_hidden: bool,
+    pub inspected_piece: Option<usize>,
+    pub filter: Option<PieceFilter>,
 }
 
 pub struct ViewportSeed<Ray>
@@ -348,20 +763,75 @@ where
     pub conjugate: Ray::Conjugate,
     pub stickers: Vec<StickerSeed<Ray>>,
     // This one only applies to the puzzle in this viewport, if the appropriate setting is chosen.
-    pub key_layers: Vec<HashMap<Key, Vec<i8>>>,
+    pub key_layers: Vec<Vec<Grip>>,
 }
 
 impl<Ray: ConcreteRaySystem> PuzzleViewport<Ray> {
+    /// Finds the stickers of each position and piece, so twists can find the stickers they move.
+    /// The puzzle has to be the one the viewport shows, with its final positions.
+    pub fn index_stickers(&mut self, puzzle: &Puzzle<Ray>) {
+        self.slot_stickers = vec![vec![]; puzzle.pieces.len()];
+        self.core_stickers = vec![vec![]; puzzle.pieces.len()];
+        for (i, sticker) in self.stickers.iter().enumerate() {
+            let slot = puzzle
+                .position_slot(sticker.piece_ind.position())
+                .expect("the puzzle should have the positions of its stickers");
+            // core stickers stay with the piece solved at their position, whose index is the slot
+            match sticker.piece_ind {
+                StickerInd::Normal(_) => self.slot_stickers[slot].push(i),
+                StickerInd::Core(_) => self.core_stickers[slot].push(i),
+            }
+        }
+    }
+
+    /// The indices in `stickers` of the stickers showing the piece.
+    pub fn piece_stickers<'a>(
+        &'a self,
+        puzzle: &Puzzle<Ray>,
+        piece: usize,
+    ) -> impl Iterator<Item = usize> + 'a {
+        let slot = puzzle.piece_slot(piece);
+        self.slot_stickers[slot]
+            .iter()
+            .chain(&self.core_stickers[piece])
+            .copied()
+    }
+
+    /// Marks the stickers of the pieces in these layers to be drawn again.
+    pub fn mark_layers(&mut self, puzzle: &Puzzle<Ray>, (face, grips): &(Ray, Vec<Grip>)) {
+        for grip in grips {
+            for &piece in puzzle.pieces_in_layer(*face, grip) {
+                let stickers: Vec<usize> = self.piece_stickers(puzzle, piece).collect();
+                self.dirty.extend(stickers);
+            }
+        }
+    }
+
     pub fn make_camera(&self, cam: &CameraFacing) -> Camera {
-        Camera::new_perspective(
-            self.viewport,
-            cam.position,
-            cam.target,
-            cam.up,
-            self.abstract_viewport.camera_height(),
-            0.1,
-            1000.0,
-        )
+        let half_height = Deg::tan(self.abstract_viewport.camera_height() / 2.0) / cam.zoom;
+        if cam.orthographic {
+            // as tall as what the perspective camera sees at the target
+            let distance = (cam.position - cam.target).magnitude();
+            Camera::new_orthographic(
+                self.viewport,
+                cam.position,
+                cam.target,
+                cam.up,
+                2.0 * distance * half_height,
+                0.1,
+                1000.0,
+            )
+        } else {
+            Camera::new_perspective(
+                self.viewport,
+                cam.position,
+                cam.target,
+                cam.up,
+                Deg::atan(half_height) * 2.0,
+                0.1,
+                1000.0,
+            )
+        }
     }
 }
 
@@ -381,15 +851,49 @@ impl<Ray: ConcreteRaySystem> PuzzleViewport<Ray> {
             })
             .map(|ds| ds.1)
     }
+
+    /// The turn that moves the sticker along the screen direction `drag` the most.
+    /// Turns on the axis the sticker is on are not considered.
+    pub fn drag_turn(
+        &self,
+        camera: &Camera,
+        sticker: &Sticker<Ray>,
+        drag: Vec2,
+    ) -> Option<(Ray, i8)> {
+        const EPSILON: f32 = 1e-4;
+        let center = sticker
+            .vertices
+            .iter()
+            .fold(Vec3::zero(), |sum, &vertex| sum + vertex)
+            / sticker.vertices.len() as f32;
+        let start = camera.pixel_at_position(center);
+        let drag = drag.normalize();
+        Ray::AXIS_HEADS
+            .iter()
+            .filter(|axis| axis.get_axis()[0] != sticker.face.get_axis()[0])
+            .filter_map(|&axis| {
+                let ConcreteTurn::Rotation(normal, angle) =
+                    Ray::turn_to_concrete((axis, 1), self.conjugate)
+                else {
+                    return None;
+                };
+                let velocity = normal.cross(center) * angle.signum();
+                let end = camera.pixel_at_position(center + velocity * 0.01);
+                let motion = vec2(end.x - start.x, end.y - start.y);
+                (motion.magnitude() > EPSILON).then(|| (motion.normalize().dot(drag), axis))
+            })
+            .max_by(|(a1, _), (a2, _)| a1.abs().partial_cmp(&a2.abs()).expect("not nan"))
+            .map(|(alignment, axis)| (axis, if alignment > 0.0 { 1 } else { -1 }))
+    }
 }
 
 pub struct PuzzleSeed<Ray>
 where
     Ray: ConcreteRaySystem,
 {
-    pub grips: Vec<Vec<i8>>,
+    pub grips: Vec<Grip>,
     pub viewports: Vec<ViewportSeed<Ray>>,
-    pub key_layers: Vec<HashMap<Key, Vec<i8>>>,
+    pub key_layers: Vec<Vec<Grip>>,
 }
 
 pub struct ConcretePuzzle<Ray>
@@ -398,34 +902,45 @@ where
 {
     pub puzzle: Puzzle<Ray>,
     pub viewports: Vec<PuzzleViewport<Ray>>,
-    /// The nth entry in here is a HashMap mapping keys to layers,
+    /// The nth entry in here lists the grip of each layer number (starting at 1),
     /// for rays that are the nth in their axis.
-    pub key_layers: Vec<HashMap<Key, Vec<i8>>>, // Key is a keyboard key and a HashMap key!
+    pub key_layers: Vec<Vec<Grip>>,
 }
 
 impl<Ray: ConcreteRaySystem> ConcretePuzzle<Ray> {
-    pub fn twist(&mut self, (ray, order): (Ray, i8), grip: &[i8], animation_length: f32) {
+    pub fn twist(&mut self, (ray, order): (Ray, i8), grip: &Grip, animation_length: f32) {
         self.puzzle.twist((ray, order), grip);
-        let permutation = self.puzzle.permutation();
+        // the pieces are still in the layer after the twist, in their new positions
+        let pieces = self.puzzle.pieces_in_layer(ray, grip);
         for viewport in self.viewports.iter_mut() {
-            for sticker in viewport.stickers.iter_mut() {
-                let piece_at_sticker = self.puzzle.piece_by_ind(sticker.piece_ind, &permutation);
-                if piece_at_sticker.grip_on_axis(ray) == grip {
-                    let turn = Ray::turn_to_concrete((ray, order), viewport.conjugate).mod_angle();
-                    sticker.animation = Some(StickerAnimation {
+            let turn = Ray::turn_to_concrete((ray, order), viewport.conjugate).mod_angle();
+            for &piece in pieces {
+                let stickers: Vec<usize> = viewport.piece_stickers(&self.puzzle, piece).collect();
+                for &i in &stickers {
+                    viewport.stickers[i].animation = Some(StickerAnimation {
                         turn,
                         time_remaining: animation_length,
                     })
                 }
+                viewport.dirty.extend(stickers);
             }
         }
     }
 
+    pub fn is_animating(&self) -> bool {
+        self.viewports
+            .iter()
+            .any(|viewport| viewport.stickers.iter().any(|s| s.animation.is_some()))
+    }
+
+    /// Stops the animations and draws every sticker again,
+    /// for when the puzzle changes without twisting.
     pub fn reset_animations(&mut self) {
         for viewport in self.viewports.iter_mut() {
             for sticker in viewport.stickers.iter_mut() {
                 sticker.animation = None;
             }
+            viewport.look = None;
         }
     }
 }
@@ -434,6 +949,17 @@ pub fn polygon_inds(verts: usize) -> Vec<[usize; 3]> {
     (2..verts).map(|i| [0, i - 1, i]).collect()
 }
 
+/// The triangles of the ring between a polygon and a smaller copy of it.
+/// The first `n` positions are the outer polygon and the next `n` the inner one.
+pub fn ring_inds(n: usize) -> Vec<[usize; 3]> {
+    (0..n)
+        .flat_map(|i| {
+            let j = (i + 1) % n;
+            [[i, j, n + j], [i, n + j, n + i]]
+        })
+        .collect()
+}
+
 pub mod concrete_ray_system_tests {
     use super::*;
     use crate::enum_iter;
@@ -479,6 +1005,62 @@ pub mod concrete_ray_system_tests {
         }
     }
 
+    fn mirror_reverses_turns<Ray>()
+    where
+        Ray: ConcreteRaySystem + std::fmt::Debug,
+        Ray::Conjugate: std::fmt::Debug,
+    {
+        let mirror = Symmetry::<Ray>::mirror();
+        for conjugate in enum_iter::<Ray::Conjugate>() {
+            assert!(
+                Symmetry::<Ray>::from_concrete(ConcreteTurn::Reflection(Vec3::unit_x()), conjugate)
+                    .is_some(),
+                "conjugate {conjugate:?} is not symmetric across the x axis"
+            );
+        }
+        // half turns have no direction
+        if Ray::order() == 2 {
+            return;
+        }
+        for ray in enum_iter::<Ray>() {
+            // a clockwise turn of a face is sent to a counterclockwise turn of its mirror image
+            let ((new_ray, order), _) = mirror.apply_twist(&((ray, ray.clockwise()), vec![]));
+            assert_eq!(
+                order * new_ray.clockwise(),
+                -1,
+                "mirroring does not reverse turns around {ray:?}"
+            );
+        }
+    }
+
+    fn cameras_look_at_rays<Ray>()
+    where
+        Ray: ConcreteRaySystem + std::fmt::Debug,
+        Ray::Conjugate: std::fmt::Debug,
+    {
+        for conjugate in enum_iter::<Ray::Conjugate>() {
+            for ray in enum_iter::<Ray>() {
+                let mut facing = CameraFacing::default();
+                facing.look_at_ray(ray, conjugate);
+                let pointing = (facing.position - facing.target).normalize();
+                assert!(
+                    pointing.distance(ray.ray_to_vec(conjugate)) < EPSILON,
+                    "camera does not look at {ray:?} in conjugate {conjugate:?}",
+                );
+                assert!(
+                    facing.up.cross(pointing).magnitude() > EPSILON,
+                    "camera looking at {ray:?} has no up direction",
+                );
+
+                let saved = serde_json::to_string(&facing).unwrap();
+                assert_eq!(
+                    serde_json::from_str::<CameraFacing>(&saved).unwrap(),
+                    facing
+                );
+            }
+        }
+    }
+
     pub fn validate_concrete_ray_system<Ray>()
     where
         Ray: ConcreteRaySystem + std::fmt::Debug,
@@ -486,5 +1068,7 @@ pub mod concrete_ray_system_tests {
     {
         ray_vectors_unit::<Ray>();
         turn_matrix_matches_abstract::<Ray>();
+        mirror_reverses_turns::<Ray>();
+        cameras_look_at_rays::<Ray>();
     }
 }
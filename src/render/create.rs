@@ -65,44 +65,49 @@ fn make_viewport(
     }
 }
 
-pub fn create_sticker_gm<Ray: ConcreteRaySystem>(
-    context: &Context,
-    vertices: &Vec<Vec3>,
-    color: Ray,
-    prefs: &Preferences,
-) -> Gm<Mesh, ColorMaterial> {
-    let mut cpu_mesh = CpuMesh {
-        positions: Positions::F32(vertices.clone()),
-        indices: Indices::U8(
-            polygon_inds(vertices.len())
-                .into_iter()
-                .flatten()
-                .map(|e| e as u8)
-                .collect(),
-        ),
-        ..Default::default()
-    };
-    cpu_mesh.compute_normals();
-    Gm::new(
-        Mesh::new(context, &cpu_mesh),
-        ColorMaterial {
-            color: Ray::ray_to_color(prefs)[color].to_srgba(),
-            render_states: RenderStates {
-                cull: Cull::Back,
-                ..Default::default()
-            },
-            ..Default::default()
-        },
-    )
+/// How far the plastic behind stickers is pushed towards the center, relative to the puzzle size
+const INTERNAL_FACE_DEPTH: f32 = 0.005;
+
+/// Scales the polygon towards its center.
+pub fn shrink_polygon(vertices: &[Vec3], scale: f32) -> Vec<Vec3> {
+    let center = vertices.iter().fold(Vec3::zero(), |sum, &v| sum + v) / vertices.len() as f32;
+    vertices
+        .iter()
+        .map(|&v| center + (v - center) * scale)
+        .collect()
+}
+
+/// The shape of a sticker with the gaps, outline and plastic from the preferences.
+pub fn sticker_shape(vertices: &[Vec3], prefs: &Preferences) -> StickerShape {
+    let visuals = &prefs.visuals;
+    let outer = shrink_polygon(vertices, 1.0 - visuals.sticker_gap);
+    let inner = shrink_polygon(&outer, 1.0 - visuals.outline_width);
+
+    StickerShape {
+        outline: (visuals.outline_width > 0.0)
+            .then(|| outer.iter().chain(&inner).copied().collect()),
+        internal_face: visuals.internal_faces.then(|| {
+            vertices
+                .iter()
+                .map(|&v| v * (1.0 - INTERNAL_FACE_DEPTH))
+                .collect()
+        }),
+        main: inner,
+        outline_color: visuals.outline_color.to_srgba(),
+    }
 }
 
 pub fn make_concrete_puzzle<Ray: ConcreteRaySystem>(
     window_size: (u32, u32),
-    context: &Context,
     mut puzzle_seed: PuzzleSeed<Ray>,
     prefs: &Preferences,
 ) -> ConcretePuzzle<Ray> {
-    let puzzle: Puzzle<Ray> = Puzzle::make_solved(puzzle_seed.grips);
+    let mut puzzle: Puzzle<Ray> = if prefs.concrete.sparse {
+        // the pieces are added once the stickers are known
+        Puzzle::make_sparse(puzzle_seed.grips, [])
+    } else {
+        Puzzle::make_solved(puzzle_seed.grips)
+    };
 
     let top_viewport = make_top_viewport(
         &puzzle_seed
@@ -112,7 +117,7 @@ pub fn make_concrete_puzzle<Ray: ConcreteRaySystem>(
             .collect::<Vec<_>>()[..],
     );
 
-    let viewports = puzzle_seed
+    let mut viewports = puzzle_seed
         .viewports
         .iter_mut()
         .map(|viewport_seed| {
@@ -145,7 +150,7 @@ pub fn make_concrete_puzzle<Ray: ConcreteRaySystem>(
                     let piece_ind =
                         puzzle.piece_to_index(&Piece::make_solved_from_layers(seed_layers_clone));
 
-                    let gm = create_sticker_gm(context, &seed.vertices, seed.color, prefs);
+                    let shape = sticker_shape(&seed.vertices, prefs);
                     //dbg!(seed.options.parity, i, &seed.vertices, &vertices);
 
                     stickers.push(Sticker {
@@ -157,8 +162,9 @@ pub fn make_concrete_puzzle<Ray: ConcreteRaySystem>(
                         face: seed.face,
                         color: seed.color,
                         vertices: seed.vertices.clone(),
-                        gm,
+                        shape,
                         animation: None,
+                        drawn: None,
                     });
                 }
             }
@@ -178,9 +184,26 @@ pub fn make_concrete_puzzle<Ray: ConcreteRaySystem>(
                 conjugate: viewport_seed.conjugate,
                 stickers,
                 key_layers: viewport_seed.key_layers.clone(),
+                mesh: None,
+                slot_stickers: vec![],
+                core_stickers: vec![],
+                look: None,
+                hovered_layers: None,
+                dirty: vec![],
             }
         })
-        .collect();
+        .collect::<Vec<PuzzleViewport<Ray>>>();
+
+    if prefs.concrete.sparse {
+        let positions = viewports
+            .iter()
+            .flat_map(|viewport| &viewport.stickers)
+            .map(|sticker| sticker.piece_ind.position());
+        puzzle = Puzzle::make_sparse(puzzle.grips.clone(), positions);
+    }
+    for viewport in viewports.iter_mut() {
+        viewport.index_stickers(&puzzle);
+    }
 
     ConcretePuzzle {
         puzzle,
@@ -220,3 +243,29 @@ pub fn update_viewports<Ray: ConcreteRaySystem>(
         )*/
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sticker_outlines() {
+        let square = vec![
+            vec3(-1.0, -1.0, 1.0),
+            vec3(1.0, -1.0, 1.0),
+            vec3(1.0, 1.0, 1.0),
+            vec3(-1.0, 1.0, 1.0),
+        ];
+        let inner = shrink_polygon(&square, 0.5);
+        assert_eq!(inner[2], vec3(0.5, 0.5, 1.0));
+
+        let positions: Vec<Vec3> = square.iter().chain(&inner).copied().collect();
+        let inds = ring_inds(square.len());
+        assert_eq!(inds.len(), 8);
+        // the ring faces the same way as the sticker
+        for [a, b, c] in inds {
+            let normal = (positions[b] - positions[a]).cross(positions[c] - positions[a]);
+            assert!(normal.z > 0.0);
+        }
+    }
+}
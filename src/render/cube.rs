@@ -1,18 +1,14 @@
 use crate::preferences::ConcretePuzzlePreferences;
-use crate::puzzle::common::RaySystem;
+use crate::puzzle::common::{Grip, RaySystem};
 use crate::puzzle::cube::CubeRay;
 use crate::puzzle::cube::{Basis, Sign};
 use crate::render::common::*;
-use crate::NUMBER_KEYS;
 use enum_map::enum_map;
-use std::collections::HashMap;
 use std::f32::consts::PI;
 
 use crate::preferences::Preferences;
 use crate::util::{color, Vec3};
 
-const SUPER_START: f32 = 0.75;
-
 impl ConcreteRaySystem for CubeRay {
     type Conjugate = ();
 
@@ -44,14 +40,16 @@ impl ConcreteRaySystem for CubeRay {
     }
 }
 
-pub fn nnn_seeds(order: i8, _prefs: &ConcretePuzzlePreferences) -> PuzzleSeed<CubeRay> {
+pub fn nnn_seeds(order: i8, prefs: &ConcretePuzzlePreferences) -> PuzzleSeed<CubeRay> {
     use crate::puzzle::cube::name::*;
 
+    let super_start = prefs.cube_super_start;
+
     let mut current_width = 0.0;
 
-    let grips: Vec<Vec<i8>> = (-order + 1..=order - 1)
+    let grips: Vec<Grip> = (-order + 1..=order - 1)
         .step_by(2)
-        .map(|k| vec![k, -k])
+        .map(|k| [k, -k])
         .collect();
 
     let mut viewports: Vec<ViewportSeed<CubeRay>> = vec![];
@@ -99,9 +97,9 @@ pub fn nnn_seeds(order: i8, _prefs: &ConcretePuzzlePreferences) -> PuzzleSeed<Cu
                         color: U,
                         vertices: vec![
                             cv(-1.0, -1.0),
-                            cv(SUPER_START, -1.0),
-                            cv(SUPER_START, SUPER_START),
-                            cv(-1.0, SUPER_START),
+                            cv(super_start, -1.0),
+                            cv(super_start, super_start),
+                            cv(-1.0, super_start),
                         ],
                         options: Default::default(),
                     });
@@ -110,10 +108,10 @@ pub fn nnn_seeds(order: i8, _prefs: &ConcretePuzzlePreferences) -> PuzzleSeed<Cu
                         face: U,
                         color: R,
                         vertices: vec![
-                            cv(SUPER_START, -1.0),
+                            cv(super_start, -1.0),
                             cv(1.0, -1.0),
                             cv(1.0, 1.0),
-                            cv(SUPER_START, SUPER_START),
+                            cv(super_start, super_start),
                         ],
                         options: Default::default(),
                     });
@@ -122,8 +120,8 @@ pub fn nnn_seeds(order: i8, _prefs: &ConcretePuzzlePreferences) -> PuzzleSeed<Cu
                         face: U,
                         color: B,
                         vertices: vec![
-                            cv(-1.0, SUPER_START),
-                            cv(SUPER_START, SUPER_START),
+                            cv(-1.0, super_start),
+                            cv(super_start, super_start),
                             cv(1.0, 1.0),
                             cv(-1.0, 1.0),
                         ],
@@ -137,8 +135,8 @@ pub fn nnn_seeds(order: i8, _prefs: &ConcretePuzzlePreferences) -> PuzzleSeed<Cu
                         color: U,
                         vertices: vec![
                             cv(-1.0, -1.0),
-                            cv(SUPER_START, -1.0),
-                            cv(SUPER_START, 1.0),
+                            cv(super_start, -1.0),
+                            cv(super_start, 1.0),
                             cv(-1.0, 1.0),
                         ],
                         options: Default::default(),
@@ -148,10 +146,10 @@ pub fn nnn_seeds(order: i8, _prefs: &ConcretePuzzlePreferences) -> PuzzleSeed<Cu
                         face: U,
                         color: R,
                         vertices: vec![
-                            cv(SUPER_START, -1.0),
+                            cv(super_start, -1.0),
                             cv(1.0, -1.0),
                             cv(1.0, 1.0),
-                            cv(SUPER_START, 1.0),
+                            cv(super_start, 1.0),
                         ],
                         options: Default::default(),
                     });
@@ -166,8 +164,8 @@ pub fn nnn_seeds(order: i8, _prefs: &ConcretePuzzlePreferences) -> PuzzleSeed<Cu
                 color: U,
                 vertices: vec![
                     Vec3::new(0.0, 0.0, 1.0) * cube_scale,
-                    Vec3::new(SUPER_START * si, -SUPER_START * si, 1.0) * cube_scale,
-                    Vec3::new(SUPER_START * si, SUPER_START * si, 1.0) * cube_scale,
+                    Vec3::new(super_start * si, -super_start * si, 1.0) * cube_scale,
+                    Vec3::new(super_start * si, super_start * si, 1.0) * cube_scale,
                 ],
                 options: Default::default(),
             });
@@ -176,24 +174,18 @@ pub fn nnn_seeds(order: i8, _prefs: &ConcretePuzzlePreferences) -> PuzzleSeed<Cu
                 face: U,
                 color: R,
                 vertices: vec![
-                    Vec3::new(SUPER_START * si, -SUPER_START * si, 1.0) * cube_scale,
+                    Vec3::new(super_start * si, -super_start * si, 1.0) * cube_scale,
                     Vec3::new(si, -si, 1.0) * cube_scale,
                     Vec3::new(si, si, 1.0) * cube_scale,
-                    Vec3::new(SUPER_START * si, SUPER_START * si, 1.0) * cube_scale,
+                    Vec3::new(super_start * si, super_start * si, 1.0) * cube_scale,
                 ],
                 options: Default::default(),
             });
         }
 
         let key_layers = vec![
-            HashMap::from_iter((-n..=n).rev().step_by(2).map(|nn| {
-                let layer = (n - nn) / 2;
-                (NUMBER_KEYS[layer as usize], vec![nn, -nn])
-            })),
-            HashMap::from_iter((-n..=n).step_by(2).map(|nn| {
-                let layer = (n + nn) / 2;
-                (NUMBER_KEYS[layer as usize], vec![nn, -nn])
-            })),
+            (-n..=n).rev().step_by(2).map(|nn| [nn, -nn]).collect(),
+            (-n..=n).step_by(2).map(|nn| [nn, -nn]).collect(),
         ];
 
         viewports.push(ViewportSeed {
@@ -204,10 +196,7 @@ pub fn nnn_seeds(order: i8, _prefs: &ConcretePuzzlePreferences) -> PuzzleSeed<Cu
         });
     }
 
-    let key_layers = vec![
-        HashMap::from_iter(NUMBER_KEYS.into_iter().zip(grips.iter().rev().cloned())),
-        HashMap::from_iter(NUMBER_KEYS.into_iter().zip(grips.iter().cloned())),
-    ];
+    let key_layers = vec![grips.iter().rev().cloned().collect(), grips.clone()];
 
     PuzzleSeed {
         grips,
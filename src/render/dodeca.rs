@@ -1,13 +1,11 @@
 use crate::enum_iter;
 use crate::preferences::ConcretePuzzlePreferences;
 use crate::preferences::Preferences;
-use crate::puzzle::common::RaySystem;
 use crate::puzzle::common::{Basis, BasisDiff, Sign};
+use crate::puzzle::common::{Grip, RaySystem};
 use crate::puzzle::dodeca::DodecaRay;
 use crate::render::common::*;
-use crate::NUMBER_KEYS;
 use enum_map::enum_map;
-use std::collections::HashMap;
 use std::f32::consts::PI;
 
 use crate::util::{color, Vec3};
@@ -140,14 +138,11 @@ fn bary(scale: f32, c1: f32, c2: f32, c3: f32, c4: f32, c5: f32) -> Vec3 {
 pub fn pentultimate_seeds(_prefs: &ConcretePuzzlePreferences) -> PuzzleSeed<DodecaRay> {
     use crate::puzzle::dodeca::name::*;
 
-    let grips: Vec<Vec<i8>> = vec![vec![-1, 1], vec![1, -1]];
+    let grips: Vec<Grip> = vec![[-1, 1], [1, -1]];
 
     let mut viewports: Vec<ViewportSeed<DodecaRay>> = vec![];
 
-    let key_layers = vec![
-        HashMap::from([(NUMBER_KEYS[0], vec![1, -1]), (NUMBER_KEYS[1], vec![-1, 1])]),
-        HashMap::from([(NUMBER_KEYS[0], vec![-1, 1]), (NUMBER_KEYS[1], vec![1, -1])]),
-    ];
+    let key_layers = vec![vec![[1, -1], [-1, 1]], vec![[-1, 1], [1, -1]]];
 
     let bary = |a, b, c, d, e| bary(SCALE_CIRCUMRAD, a, b, c, d, e);
 
@@ -182,7 +177,7 @@ pub fn pentultimate_seeds(_prefs: &ConcretePuzzlePreferences) -> PuzzleSeed<Dode
                         ..Default::default()
                     },
                 }],
-                key_layers: vec![HashMap::new(), HashMap::new()],
+                key_layers: vec![vec![], vec![]],
             });
         }
 
@@ -300,21 +295,13 @@ const SLICE_SUPER: f32 = 0.05;
 pub fn mega_seeds(_prefs: &ConcretePuzzlePreferences) -> PuzzleSeed<DodecaRay> {
     use crate::puzzle::dodeca::name::*;
 
-    let grips: Vec<Vec<i8>> = vec![vec![-2, 2], vec![0, 0], vec![2, -2]];
+    let grips: Vec<Grip> = vec![[-2, 2], [0, 0], [2, -2]];
 
     let mut viewports: Vec<ViewportSeed<DodecaRay>> = vec![];
 
     let key_layers = vec![
-        HashMap::from([
-            (NUMBER_KEYS[0], vec![2, -2]),
-            (NUMBER_KEYS[1], vec![0, 0]),
-            (NUMBER_KEYS[2], vec![-2, 2]),
-        ]),
-        HashMap::from([
-            (NUMBER_KEYS[0], vec![-2, 2]),
-            (NUMBER_KEYS[1], vec![0, 0]),
-            (NUMBER_KEYS[2], vec![2, -2]),
-        ]),
+        vec![[2, -2], [0, 0], [-2, 2]],
+        vec![[-2, 2], [0, 0], [2, -2]],
     ];
 
     for conj in enum_iter::<BinaryConjugate>() {
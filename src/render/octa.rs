@@ -1,13 +1,11 @@
 use crate::preferences::ConcretePuzzlePreferences;
 use crate::preferences::Preferences;
-use crate::puzzle::common::RaySystem;
 use crate::puzzle::common::{Basis, Sign};
+use crate::puzzle::common::{Grip, RaySystem};
 use crate::puzzle::octa::OctaRay;
 use crate::render::common::*;
-use crate::NUMBER_KEYS;
 use core::f32::consts::PI;
 use enum_map::enum_map;
-use std::collections::HashMap;
 
 use crate::util::{color, Vec3};
 use cgmath::InnerSpace;
@@ -49,7 +47,7 @@ impl ConcreteRaySystem for OctaRay {
     let r_BU = OctaRay::from_name("BU").unwrap();
     #[allow(non_snake_case)]
     let r_U = OctaRay::from_name("U").unwrap();
-    let grips: Vec<Vec<i8>> = vec![vec![0, 0]];
+    let grips: Vec<Grip> = vec![[0, 0]];
 
     let viewports = vec![{
         let abstract_viewport = AbstractViewport {
@@ -87,11 +85,11 @@ impl ConcreteRaySystem for OctaRay {
             abstract_viewport,
             conjugate: (),
             stickers,
-            default_layers: vec![vec![0, 0]],
+            default_layers: vec![[0, 0]],
         }
     }];
 
-    let key_layers = vec![HashMap::from_iter(vec![]), HashMap::from_iter(vec![])];
+    let key_layers = vec![vec![], vec![]];
 
     PuzzleSeed {
         grips,
@@ -126,9 +124,9 @@ fn cut_depth_on_axis(order: i8, cut: i8) -> f32 {
 pub fn fto_seeds(order: i8, prefs: &ConcretePuzzlePreferences) -> PuzzleSeed<OctaRay> {
     use crate::puzzle::octa::name::*;
 
-    let grips: Vec<Vec<i8>> = (-order + 1..=order - 1)
+    let grips: Vec<Grip> = (-order + 1..=order - 1)
         .step_by(2)
-        .map(|k| vec![k, -k])
+        .map(|k| [k, -k])
         .collect();
 
     /*
@@ -542,14 +540,8 @@ pub fn fto_seeds(order: i8, prefs: &ConcretePuzzlePreferences) -> PuzzleSeed<Oct
             }
 
             let key_layers = vec![
-                HashMap::from_iter((m..=n).rev().step_by(2).map(|nn| {
-                    let layer = (n - nn) / 2;
-                    (NUMBER_KEYS[layer as usize], vec![nn, -nn])
-                })),
-                HashMap::from_iter((m..=n).step_by(2).map(|nn| {
-                    let layer = (-m + nn) / 2;
-                    (NUMBER_KEYS[layer as usize], vec![nn, -nn])
-                })),
+                (m..=n).rev().step_by(2).map(|nn| [nn, -nn]).collect(),
+                (m..=n).step_by(2).map(|nn| [nn, -nn]).collect(),
             ];
 
             viewports.push(ViewportSeed {
@@ -565,10 +557,7 @@ pub fn fto_seeds(order: i8, prefs: &ConcretePuzzlePreferences) -> PuzzleSeed<Oct
         current_x += current_width;
     }
 
-    let key_layers = vec![
-        HashMap::from_iter(NUMBER_KEYS.into_iter().zip(grips.iter().rev().cloned())),
-        HashMap::from_iter(NUMBER_KEYS.into_iter().zip(grips.iter().cloned())),
-    ];
+    let key_layers = vec![grips.iter().rev().cloned().collect(), grips.clone()];
 
     PuzzleSeed {
         grips,
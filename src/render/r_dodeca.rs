@@ -1,13 +1,11 @@
 use crate::enum_iter;
 use crate::preferences::ConcretePuzzlePreferences;
 use crate::preferences::Preferences;
-use crate::puzzle::common::RaySystem;
 use crate::puzzle::common::{Basis, BasisDiff, Sign};
+use crate::puzzle::common::{Grip, RaySystem};
 use crate::puzzle::r_dodeca::RDodecaRay;
 use crate::render::common::*;
-use crate::NUMBER_KEYS;
 use enum_map::enum_map;
-use std::collections::HashMap;
 use std::f32::consts::PI;
 
 use crate::util::{color, Vec3};
@@ -85,20 +83,18 @@ impl ConcreteRaySystem for RDodecaRay {
 const SHAPE_SCALE: f32 = 1.7;
 
 const CORE_SCALE: f32 = 0.4;
-const SUPER_START: f32 = 0.8;
 const RU2RI_SCALE: f32 = 0.8;
 
-pub fn little_chop_seeds(_prefs: &ConcretePuzzlePreferences) -> PuzzleSeed<RDodecaRay> {
+pub fn little_chop_seeds(prefs: &ConcretePuzzlePreferences) -> PuzzleSeed<RDodecaRay> {
     use crate::puzzle::r_dodeca::name::*;
 
-    let grips: Vec<Vec<i8>> = vec![vec![-1, 1], vec![1, -1]];
+    let super_start = prefs.little_chop_super_start;
+
+    let grips: Vec<Grip> = vec![[-1, 1], [1, -1]];
 
     let mut viewports: Vec<ViewportSeed<RDodecaRay>> = vec![];
 
-    let key_layers = vec![
-        HashMap::from([(NUMBER_KEYS[0], vec![1, -1]), (NUMBER_KEYS[1], vec![-1, 1])]),
-        HashMap::from([(NUMBER_KEYS[0], vec![-1, 1]), (NUMBER_KEYS[1], vec![1, -1])]),
-    ];
+    let key_layers = vec![vec![[1, -1], [-1, 1]], vec![[-1, 1], [1, -1]]];
 
     for conj in enum_iter::<BinaryConjugate>() {
         let make_grips = |grips: Vec<RDodecaRay>| {
@@ -142,7 +138,7 @@ pub fn little_chop_seeds(_prefs: &ConcretePuzzlePreferences) -> PuzzleSeed<RDode
                         ..Default::default()
                     },
                 }],
-                key_layers: vec![HashMap::new(), HashMap::new()],
+                key_layers: vec![vec![], vec![]],
             });
         }
 
@@ -169,12 +165,12 @@ pub fn little_chop_seeds(_prefs: &ConcretePuzzlePreferences) -> PuzzleSeed<RDode
                 color: RB.conjugate(conj),
                 vertices: vec![
                     Vec3::new(
-                        1.0 * SUPER_START + 0.5 * (1.0 - SUPER_START),
-                        0.5 * (1.0 - SUPER_START),
+                        1.0 * super_start + 0.5 * (1.0 - super_start),
+                        0.5 * (1.0 - super_start),
                         0.0,
                     ) * SHAPE_SCALE,
-                    Vec3::new(0.5, 0.5, -0.5 * SUPER_START) * SHAPE_SCALE,
-                    Vec3::new(0.5, 0.5, 0.5 * SUPER_START) * SHAPE_SCALE,
+                    Vec3::new(0.5, 0.5, -0.5 * super_start) * SHAPE_SCALE,
+                    Vec3::new(0.5, 0.5, 0.5 * super_start) * SHAPE_SCALE,
                 ],
                 options: StickerOptions {
                     parity: conj == BinaryConjugate::Conj,
@@ -187,11 +183,11 @@ pub fn little_chop_seeds(_prefs: &ConcretePuzzlePreferences) -> PuzzleSeed<RDode
                 color: UR.conjugate(conj),
                 vertices: vec![
                     Vec3::new(
-                        1.0 * SUPER_START + 0.5 * (1.0 - SUPER_START),
-                        0.5 * (1.0 - SUPER_START),
+                        1.0 * super_start + 0.5 * (1.0 - super_start),
+                        0.5 * (1.0 - super_start),
                         0.0,
                     ) * SHAPE_SCALE,
-                    Vec3::new(0.5, 0.5, 0.5 * SUPER_START) * SHAPE_SCALE,
+                    Vec3::new(0.5, 0.5, 0.5 * super_start) * SHAPE_SCALE,
                     Vec3::new(0.5, 0.5, 0.5) * SHAPE_SCALE,
                     Vec3::new(1.0, 0.0, 0.0) * SHAPE_SCALE,
                 ],
@@ -205,10 +201,10 @@ pub fn little_chop_seeds(_prefs: &ConcretePuzzlePreferences) -> PuzzleSeed<RDode
                 face: RB.conjugate(conj),
                 color: DR.conjugate(conj),
                 vertices: vec![
-                    Vec3::new(0.5, 0.5, -0.5 * SUPER_START) * SHAPE_SCALE,
+                    Vec3::new(0.5, 0.5, -0.5 * super_start) * SHAPE_SCALE,
                     Vec3::new(
-                        1.0 * SUPER_START + 0.5 * (1.0 - SUPER_START),
-                        0.5 * (1.0 - SUPER_START),
+                        1.0 * super_start + 0.5 * (1.0 - super_start),
+                        0.5 * (1.0 - super_start),
                         0.0,
                     ) * SHAPE_SCALE,
                     Vec3::new(1.0, 0.0, 0.0) * SHAPE_SCALE,
@@ -0,0 +1,19 @@
+in vec4 col;
+flat in float reflected;
+
+layout (location = 0) out vec4 outColor;
+
+vec3 srgb_to_linear(vec3 color) {
+    vec3 lo = color / 12.92;
+    vec3 hi = pow((color + 0.055) / 1.055, vec3(2.4));
+    return mix(lo, hi, step(vec3(0.04045), color));
+}
+
+void main()
+{
+    // reflections turn the triangles around, so culling is done here
+    if (gl_FrontFacing == (reflected > 0.5)) {
+        discard;
+    }
+    outColor = vec4(color_mapping(srgb_to_linear(col.rgb)), col.a);
+}
@@ -0,0 +1,53 @@
+// Each sticker's color and animation is in `stickerData`, five texels per sticker:
+// the color, then the columns of the transformation. Only stickers that change get rewritten.
+const int STICKERS_PER_ROW = 256;
+
+uniform mat4 viewProjection;
+uniform highp sampler2D stickerData;
+// 0 draws the opaque stickers with their outlines and plastic, 1 the colored part of translucent ones
+uniform int translucentPass;
+uniform vec3 lightDirection;
+// 1.0 with lighting off
+uniform float ambient;
+
+in vec3 position;
+// the direction the sticker faces, before animations
+in vec3 normal;
+in uint sticker;
+// the color of outlines and plastic, or transparent for the colored part of the sticker
+in vec4 partColor;
+
+out vec4 col;
+flat out float reflected;
+
+void main()
+{
+    int s = int(sticker);
+    ivec2 texel = ivec2(s % STICKERS_PER_ROW * 5, s / STICKERS_PER_ROW);
+    vec4 stickerColor = texelFetch(stickerData, texel, 0);
+    mat4 transformation = mat4(
+        texelFetch(stickerData, texel + ivec2(1, 0), 0),
+        texelFetch(stickerData, texel + ivec2(2, 0), 0),
+        texelFetch(stickerData, texel + ivec2(3, 0), 0),
+        texelFetch(stickerData, texel + ivec2(4, 0), 0)
+    );
+
+    // hidden stickers have no alpha, and translucent ones leave out outlines and plastic
+    bool colored = partColor.a == 0.0;
+    bool translucent = stickerColor.a < 1.0;
+    bool drawn = stickerColor.a > 0.0
+        && (translucentPass == 1 ? translucent && colored : !translucent);
+    if (!drawn) {
+        // outside the clip volume, so the triangle is thrown away
+        gl_Position = vec4(2.0, 2.0, 2.0, 1.0);
+        return;
+    }
+
+    col = colored ? stickerColor : partColor;
+    if (colored) {
+        vec3 turned = normalize((transformation * vec4(normal, 0.0)).xyz);
+        col.rgb *= ambient + (1.0 - ambient) * max(dot(turned, lightDirection), 0.0);
+    }
+    reflected = determinant(transformation) < 0.0 ? 1.0 : 0.0;
+    gl_Position = viewProjection * transformation * vec4(position, 1.0);
+}
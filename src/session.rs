@@ -1,22 +1,46 @@
+use crate::log_format::{self, LogFormat};
+use crate::preferences::Macro;
 use crate::puzzle::common::*;
 use crate::puzzle::cube::CubeRay;
 use crate::puzzle::dodeca::DodecaRay;
+use crate::puzzle::filter::PieceFilter;
+use crate::puzzle::metric::MoveCount;
+use crate::puzzle::notation;
 use crate::puzzle::octa::OctaRay;
 use crate::puzzle::r_dodeca::RDodecaRay;
+use crate::puzzle::simplify::simplify;
+use crate::puzzle::symmetry::{piece_type, turns_moving, Symmetry};
 use crate::render;
 use crate::render::common::*;
 use crate::render::create::make_concrete_puzzle;
-use crate::util::Vec3;
 use crate::Preferences;
 use crate::VERSION;
 use enum_map::EnumMap;
 use eyre::eyre;
+use std::collections::VecDeque;
+use std::fmt;
 
 pub struct Session<Ray: ConcreteRaySystem> {
     pub scramble: Vec<EnumMap<Ray, Ray>>,
+    /// The solved position of the piece of each orientation in `scramble`.
+    pub scramble_positions: Vec<usize>,
     pub concrete_puzzle: ConcretePuzzle<Ray>,
-    pub twists: Vec<((Ray, i8), Vec<Vec<i8>>)>,
-    pub undid_twists: Vec<((Ray, i8), Vec<Vec<i8>>)>,
+    pub twists: Vec<((Ray, i8), Vec<Grip>)>,
+    /// The move count of `twists`, kept up to date whenever they change.
+    move_count: MoveCount,
+    pub undid_twists: Vec<((Ray, i8), Vec<Grip>)>,
+    /// Twists waiting to be done, one at a time after each animation finishes.
+    pub queued_twists: VecDeque<Twist<Ray>>,
+    /// Index in `concrete_puzzle.puzzle.pieces` of the piece shown in the piece inspector.
+    pub inspected_piece: Option<usize>,
+    /// The type of each piece in `concrete_puzzle.puzzle.pieces`, from `symmetry::piece_type`.
+    pub piece_types: Vec<Vec<i8>>,
+    pub piece_filter: Option<PieceFilter>,
+    /// The face and grips a click at the mouse position would turn.
+    pub hovered_layers: Option<(Ray, Vec<Grip>)>,
+    pub blindfold: Option<BlindfoldPhase>,
+    /// The last finished blindfolded solve, which is saved in the log.
+    pub blindfold_result: Option<BlindfoldResult>,
     // None: the mouse is not pressed.
     // Some((conj, None)): the mouse is being held from a viewport with conjugation conj, and camera orbiting has started.
     // Some((conj, Some((loc, button)))): the mouse is being held from a viewport with conjugation conj, and camera orbiting has not yet started. the mouse was pressed at loc with button.
@@ -25,59 +49,178 @@ pub struct Session<Ray: ConcreteRaySystem> {
         Option<(three_d::LogicalPoint, three_d::MouseButton)>,
     )>,
     pub save_path: Option<std::path::PathBuf>,
+    /// The format of the file at `save_path`, which plain saving keeps.
+    pub save_format: LogFormat,
     pub version: String,
     pub camera_facings: EnumMap<Ray::Conjugate, CameraFacing>,
 }
 
-fn string_vec_to_enum_map<Ray: ConcreteRaySystem + enum_map::Enum>(
-    strs: Vec<String>,
-) -> eyre::Result<EnumMap<Ray, Ray>> {
-    if strs.len() != Ray::LENGTH {
-        return Err(eyre!("Invalid enum length"));
-    }
-    let rays: Vec<Ray> = strs
-        .into_iter()
-        .map(|st| Ray::from_name(&st).ok_or_else(|| eyre!("Invalid ray name")))
+/// The scramble and twists of a log, read for a ray system.
+struct ParsedLog<Ray: RaySystem> {
+    scramble: Vec<EnumMap<Ray, Ray>>,
+    /// The solved position of the piece of each orientation in `scramble`.
+    scramble_positions: Vec<usize>,
+    twists: Vec<Twist<Ray>>,
+}
+
+fn parse_log<Ray: ConcreteRaySystem>(log: &SessionLog) -> eyre::Result<ParsedLog<Ray>> {
+    let scramble: Vec<_> = log
+        .scramble
+        .iter()
+        .map(|ori| orientation_from_names(ori).ok_or_else(|| eyre!("Invalid orientation")))
+        .collect::<eyre::Result<_>>()?;
+    let scramble_positions = match &log.scramble_positions {
+        Some(positions) if positions.len() != scramble.len() => {
+            return Err(eyre!("Scramble positions do not match the scramble"))
+        }
+        Some(positions) => positions.clone(),
+        None => (0..scramble.len()).collect(),
+    };
+    let twists = log
+        .twists
+        .iter()
+        .map(|((name, order), grips)| {
+            let ray = Ray::from_name(name).ok_or_else(|| eyre!("Invalid ray name"))?;
+            Ok(((ray, *order), grips.clone()))
+        })
         .collect::<eyre::Result<_>>()?;
-    let map = EnumMap::from_fn(|ray| rays[Ray::into_usize(ray)]);
-    Ok(map)
+    Ok(ParsedLog {
+        scramble,
+        scramble_positions,
+        twists,
+    })
+}
+
+/// The type of each piece of the puzzle, from `symmetry::piece_type`.
+fn piece_types<Ray: RaySystem>(puzzle: &Puzzle<Ray>) -> Vec<Vec<i8>> {
+    puzzle
+        .pieces
+        .iter()
+        .map(|piece| piece_type(&piece.layers))
+        .collect()
 }
 
 impl<Ray: ConcreteRaySystem> Session<Ray> {
     pub fn from_concrete(concrete_puzzle: ConcretePuzzle<Ray>) -> Session<Ray> {
+        let piece_types = piece_types(&concrete_puzzle.puzzle);
         Session {
             scramble: concrete_puzzle.puzzle.orientations(),
+            scramble_positions: concrete_puzzle.puzzle.solved_positions().to_vec(),
             concrete_puzzle,
             twists: vec![],
+            move_count: MoveCount::default(),
             undid_twists: vec![],
+            queued_twists: VecDeque::new(),
+            inspected_piece: None,
+            piece_types,
+            piece_filter: None,
+            hovered_layers: None,
+            blindfold: None,
+            blindfold_result: None,
             mouse_press_location: None,
             save_path: None,
+            save_format: LogFormat::default(),
             version: VERSION.to_string(),
-            camera_facings: EnumMap::from_fn(|_| CameraFacing {
-                position: Vec3::new(5.0, -10.0, 4.0),
-                target: Vec3::new(0.0, 0.0, 0.0),
-                up: Vec3::new(0.0, 0.0, 1.0),
-            }),
+            camera_facings: EnumMap::from_fn(|_| CameraFacing::default()),
+        }
+    }
+
+    /// The camera facings in the order of the conjugates, for saving.
+    pub fn camera_facing_list(&self) -> Vec<CameraFacing> {
+        self.camera_facings.values().cloned().collect()
+    }
+
+    /// Sets the camera facings from a list in the order of the conjugates.
+    /// Lists for a different number of conjugates are ignored.
+    pub fn set_camera_facings(&mut self, facings: &[CameraFacing]) {
+        if facings.len() == self.camera_facings.len() {
+            for (facing, new_facing) in self.camera_facings.values_mut().zip(facings) {
+                *facing = new_facing.clone();
+            }
         }
     }
 
-    fn multi_layer_twist(&mut self, tw: (Ray, i8), grips: &Vec<Vec<i8>>, animation_length: f32) {
+    fn multi_layer_twist(&mut self, tw: (Ray, i8), grips: &Vec<Grip>, animation_length: f32) {
         for grip in grips {
-            self.concrete_puzzle.twist(tw, &grip[..], animation_length);
+            self.concrete_puzzle.twist(tw, grip, animation_length);
         }
     }
 
-    pub fn twist(&mut self, tw: (Ray, i8), grips: Vec<Vec<i8>>, animation_length: f32) {
+    /// Does and records the twist. Twisting no layers does nothing, so it is not recorded
+    /// and leaves the redo history alone.
+    pub fn twist(&mut self, tw: (Ray, i8), grips: Vec<Grip>, animation_length: f32) {
+        if grips.is_empty() {
+            return;
+        }
         self.multi_layer_twist(tw, &grips, animation_length);
         self.twists.push((tw, grips));
         self.undid_twists = vec![];
+        self.count_moves();
+    }
+
+    /// Queues twists to be done with animation, one after the other.
+    pub fn queue_twists(&mut self, twists: Vec<Twist<Ray>>) {
+        self.queued_twists.extend(twists);
+    }
+
+    /// Turns the whole puzzle. This is recorded, but does not count as a move.
+    pub fn rotate(&mut self, tw: (Ray, i8), animation_length: f32) {
+        let grips = self.concrete_puzzle.puzzle.grips.clone();
+        self.twist(tw, grips, animation_length);
+    }
+
+    /// Queues the rotations that bring the face to the top.
+    pub fn reorient(&mut self, face: Ray, top: Ray) -> eyre::Result<()> {
+        let turns =
+            turns_moving(face, top).ok_or_else(|| eyre!("{face} cannot be rotated to {top}"))?;
+        let grips = &self.concrete_puzzle.puzzle.grips;
+        let rotations = turns
+            .into_iter()
+            .map(|turn| (turn, grips.clone()))
+            .collect();
+        self.queue_twists(rotations);
+        Ok(())
+    }
+
+    /// Parses the algorithm and queues it.
+    pub fn queue_algorithm(&mut self, text: &str) -> eyre::Result<()> {
+        let twists = notation::parse(&self.concrete_puzzle.puzzle, text)?;
+        self.queue_twists(twists);
+        Ok(())
+    }
+
+    /// Parses the macro, reorients it and queues it.
+    pub fn queue_macro(&mut self, mac: &Macro) -> eyre::Result<()> {
+        let twists = notation::parse(&self.concrete_puzzle.puzzle, &mac.algorithm)
+            .map_err(|err| eyre!("Error in macro {}: {err}", mac.name))?;
+        let symmetry = Symmetry::rotations()
+            .into_iter()
+            .nth(mac.orientation)
+            .ok_or_else(|| eyre!("Invalid orientation for macro {}", mac.name))?;
+        self.queue_twists(symmetry.apply_twists(&twists));
+        Ok(())
+    }
+
+    /// Does the next queued twist if the previous animation has finished.
+    pub fn advance_queue(&mut self, animation_length: f32) {
+        if self.concrete_puzzle.is_animating() {
+            return;
+        }
+        if let Some((tw, grips)) = self.queued_twists.pop_front() {
+            self.twist(tw, grips, animation_length);
+        }
     }
 
     fn scramble_from_concrete(&mut self) {
         self.concrete_puzzle.reset_animations();
         self.scramble = self.concrete_puzzle.puzzle.orientations();
+        self.scramble_positions = self.concrete_puzzle.puzzle.solved_positions().to_vec();
         self.twists = vec![];
+        self.move_count = MoveCount::default();
         self.undid_twists = vec![];
+        self.queued_twists.clear();
+        self.blindfold = None;
+        self.blindfold_result = None;
     }
 
     pub fn scramble(&mut self) {
@@ -85,9 +228,13 @@ impl<Ray: ConcreteRaySystem> Session<Ray> {
         self.scramble_from_concrete();
     }
 
+    pub fn scramble_with<R: rand::Rng>(&mut self, rng: &mut R) {
+        self.concrete_puzzle.puzzle.scramble_with(rng);
+        self.scramble_from_concrete();
+    }
+
     pub fn reset(&mut self) {
-        let new_puzzle = Puzzle::make_solved(self.concrete_puzzle.puzzle.grips.clone());
-        self.concrete_puzzle.puzzle = new_puzzle;
+        self.concrete_puzzle.puzzle.reset();
         self.scramble_from_concrete();
     }
 
@@ -96,6 +243,7 @@ impl<Ray: ConcreteRaySystem> Session<Ray> {
             self.undid_twists.push(((ray, order), grips.clone()));
             // we want the animation this time
             self.multi_layer_twist((ray, -order), &grips, animation_length);
+            self.count_moves();
             Ok(())
         } else {
             // no undo left
@@ -108,6 +256,7 @@ impl<Ray: ConcreteRaySystem> Session<Ray> {
             self.twists.push(((ray, order), grips.clone()));
             // we want the animation this time
             self.multi_layer_twist((ray, order), &grips, animation_length);
+            self.count_moves();
             Ok(())
         } else {
             // no redo left
@@ -115,13 +264,16 @@ impl<Ray: ConcreteRaySystem> Session<Ray> {
         }
     }
 
+    /// Turns the last twist the other way. This is recorded as a second twist back past
+    /// the first, so undo goes back to the first, and simplifying merges the two.
     pub fn do_inverse(&mut self, animation_length: f32) -> eyre::Result<()> {
-        if let Some(((ray, order), grips)) = self.twists.pop() {
-            self.twists.push(((ray, -order), grips.clone()));
+        if let Some(((ray, order), grips)) = self.twists.last().cloned() {
+            self.twists.push(((ray, -2 * order), grips.clone()));
             self.undid_twists = vec![];
-            // we want the animation this time
+            // only animate the second turn
             self.multi_layer_twist((ray, -order), &grips, 0.0);
-            self.multi_layer_twist((ray, -order), &grips, animation_length); // do it again
+            self.multi_layer_twist((ray, -order), &grips, animation_length);
+            self.count_moves();
             Ok(())
         } else {
             // no undo left
@@ -129,18 +281,95 @@ impl<Ray: ConcreteRaySystem> Session<Ray> {
         }
     }
 
-    fn set_orientations(&mut self, oris: Vec<Vec<String>>) -> eyre::Result<()> {
-        let scramble = oris
-            .into_iter()
-            .map(|ori| string_vec_to_enum_map(ori))
-            .collect::<Result<_, _>>()?;
-        self.scramble = scramble;
+    /// Replaces the twists with a simplified sequence that does the same thing.
+    /// The puzzle does not change, but the undo history is lost.
+    pub fn simplify_twists(&mut self) {
+        self.twists = simplify(&self.concrete_puzzle.puzzle, &self.twists);
+        self.undid_twists = vec![];
+        self.count_moves();
+    }
+
+    /// Replaces the twists with a transformed sequence, like a mirrored or inverted one,
+    /// and redoes them from the scramble. The undo history is lost.
+    pub fn transform_twists(&mut self, transform: impl FnOnce(&[Twist<Ray>]) -> Vec<Twist<Ray>>) {
+        self.twists = transform(&self.twists);
+        self.undid_twists = vec![];
+        self.count_moves();
+        self.queued_twists.clear();
         self.apply_scramble();
-        Ok(())
+        self.apply_twists();
+    }
+
+    /// Starts memorizing for a blindfolded solve.
+    pub fn start_memo(&mut self) {
+        self.blindfold = Some(BlindfoldPhase::Memo { memo: 0.0 });
+        self.blindfold_result = None;
+    }
+
+    /// Ends memorization and hides the colors.
+    pub fn start_execution(&mut self) -> eyre::Result<()> {
+        match self.blindfold {
+            Some(BlindfoldPhase::Memo { memo }) => {
+                self.blindfold = Some(BlindfoldPhase::Execution {
+                    memo,
+                    execution: 0.0,
+                    first_twist: self.twists.len(),
+                });
+                Ok(())
+            }
+            _ => Err(eyre!("Not memorizing")),
+        }
+    }
+
+    /// Ends the blindfolded solve, shows the colors again and checks if the puzzle is solved.
+    pub fn finish_blindfold(&mut self) -> eyre::Result<BlindfoldResult> {
+        match self.blindfold {
+            Some(BlindfoldPhase::Execution {
+                memo,
+                execution,
+                first_twist,
+            }) => {
+                let result = BlindfoldResult {
+                    memo_ms: memo as u64,
+                    execution_ms: execution as u64,
+                    first_twist,
+                    solved: self.concrete_puzzle.puzzle.is_solved(),
+                };
+                self.blindfold = None;
+                self.blindfold_result = Some(result);
+                Ok(result)
+            }
+            _ => Err(eyre!("Not executing")),
+        }
+    }
+
+    /// Counts up the timer of the current blindfolded phase.
+    pub fn advance_blindfold(&mut self, elapsed_time: f64) {
+        match &mut self.blindfold {
+            Some(BlindfoldPhase::Memo { memo }) => *memo += elapsed_time,
+            Some(BlindfoldPhase::Execution { execution, .. }) => *execution += elapsed_time,
+            None => (),
+        }
+    }
+
+    /// Whether the colors have to be hidden for a blindfolded solve.
+    pub fn colors_hidden(&self) -> bool {
+        matches!(self.blindfold, Some(BlindfoldPhase::Execution { .. }))
+    }
+
+    pub fn move_count(&self) -> MoveCount {
+        self.move_count
+    }
+
+    /// Counts the moves again after `twists` changed.
+    fn count_moves(&mut self) {
+        self.move_count = MoveCount::count(&self.concrete_puzzle.puzzle, &self.twists);
     }
 
     fn apply_scramble(&mut self) {
-        self.concrete_puzzle.puzzle.set_orientations(&self.scramble);
+        self.concrete_puzzle
+            .puzzle
+            .set_position_orientations(&self.scramble_positions, &self.scramble);
     }
 
     fn apply_twists(&mut self) {
@@ -152,12 +381,15 @@ impl<Ray: ConcreteRaySystem> Session<Ray> {
         self.concrete_puzzle.reset_animations();
     }
 
-    fn extract_log(&self) -> (Vec<Vec<String>>, Vec<((String, i8), Vec<Vec<i8>>)>) {
-        let scramble_str = self
-            .scramble
-            .iter()
-            .map(|ori| ori.values().map(|ray| ray.name()).collect())
-            .collect();
+    /// The scramble, its positions (left out for full puzzles) and the twists, for the log.
+    fn extract_log(
+        &self,
+    ) -> (
+        Vec<NamedOrientation>,
+        Option<Vec<usize>>,
+        Vec<((String, i8), Vec<Grip>)>,
+    ) {
+        let scramble_str = self.scramble.iter().map(orientation_to_names).collect();
 
         let twists_str = self
             .twists
@@ -165,32 +397,37 @@ impl<Ray: ConcreteRaySystem> Session<Ray> {
             .map(|((ray, order), grips)| ((ray.name(), *order), grips.clone()))
             .collect();
 
-        (scramble_str, twists_str)
+        let full = (0..self.scramble.len()).eq(self.scramble_positions.iter().copied());
+        let positions = (!full).then(|| self.scramble_positions.clone());
+
+        (scramble_str, positions, twists_str)
     }
 
     fn process_log(&mut self, log: SessionLog) -> eyre::Result<()> {
-        self.version = log.version;
+        self.version = log.version.clone();
         let suffix = if self.version == VERSION {
             "".to_string()
         } else {
             format!(" (loading from version {})", self.version)
         };
 
-        self.set_orientations(log.scramble)
-            .map_err(|err| eyre!(err.to_string() + &suffix))?;
-
-        for ((st, order), grips) in log.twists {
-            self.twist(
-                (
-                    Ray::from_name(&st).ok_or_else(|| eyre!("Invalid ray name{suffix}"))?,
-                    order,
-                ),
-                grips,
-                0.0,
-            )
+        let parsed = parse_log::<Ray>(&log).map_err(|err| eyre!(err.to_string() + &suffix))?;
+        self.scramble = parsed.scramble;
+        self.scramble_positions = parsed.scramble_positions;
+        self.apply_scramble();
+        // counting the moves once at the end, not after every twist
+        for (ray_order, grips) in parsed.twists {
+            self.multi_layer_twist(ray_order, &grips, 0.0);
+            self.twists.push((ray_order, grips));
         }
+        self.undid_twists = vec![];
+        self.count_moves();
 
         self.concrete_puzzle.reset_animations();
+        self.blindfold_result = log.blindfold;
+        if let Some(camera) = log.camera {
+            self.set_camera_facings(&camera);
+        }
 
         Ok(())
     }
@@ -202,11 +439,52 @@ impl<Ray: ConcreteRaySystem> Session<Ray> {
         // this could probably be done better by only replacing self.concrete_puzzle.viewports,
         // but this is easier
         self.concrete_puzzle = new_concrete_puzzle;
+        if self.piece_types.len() != self.concrete_puzzle.puzzle.pieces.len() {
+            // it was switched between sparse and full
+            self.piece_types = piece_types(&self.concrete_puzzle.puzzle);
+            self.inspected_piece = None;
+        }
         self.apply_scramble();
         self.apply_twists();
     }
 }
 
+/// The phase of a blindfolded solve. Times are in milliseconds.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BlindfoldPhase {
+    Memo {
+        memo: f64,
+    },
+    Execution {
+        memo: f64,
+        execution: f64,
+        /// Index of the first twist done blindfolded
+        first_twist: usize,
+    },
+}
+
+/// A finished blindfolded solve.
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct BlindfoldResult {
+    pub memo_ms: u64,
+    pub execution_ms: u64,
+    /// Index of the first twist done blindfolded
+    pub first_twist: usize,
+    pub solved: bool,
+}
+
+impl fmt::Display for BlindfoldResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} (memo {:.2}s, execution {:.2}s)",
+            if self.solved { "Solved" } else { "DNF" },
+            self.memo_ms as f64 / 1000.0,
+            self.execution_ms as f64 / 1000.0
+        )
+    }
+}
+
 #[derive(Debug, Copy, Clone, serde::Serialize, serde::Deserialize)]
 pub enum CubePuzzle {
     Nnn(i8),
@@ -237,6 +515,59 @@ pub enum SessionType {
     RDodeca(RDodecaPuzzle),
 }
 
+/// The layer counts offered for cubes and octahedra. Fewer layers leave nothing to turn,
+/// and more are slow to mesh.
+pub const LAYER_RANGE: std::ops::RangeInclusive<i8> = 2..=17;
+
+/// Short spec of the puzzle type, like `cube:3` or `megaminx`.
+impl fmt::Display for SessionType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SessionType::Cube(CubePuzzle::Nnn(n)) => write!(f, "cube:{n}"),
+            SessionType::Octa(OctaPuzzle::Fto(n)) => write!(f, "fto:{n}"),
+            SessionType::Dodeca(DodecaPuzzle::Pentultimate) => write!(f, "pentultimate"),
+            SessionType::Dodeca(DodecaPuzzle::Megaminx) => write!(f, "megaminx"),
+            SessionType::RDodeca(RDodecaPuzzle::LittleChop) => write!(f, "little-chop"),
+        }
+    }
+}
+
+impl std::str::FromStr for SessionType {
+    type Err = eyre::Report;
+
+    /// Reads a spec written by `Display`, like `cube:5`.
+    fn from_str(spec: &str) -> eyre::Result<Self> {
+        let layers = |n: &str| match n.parse::<i8>() {
+            Ok(n) if LAYER_RANGE.contains(&n) => Ok(n),
+            _ => Err(eyre!(
+                "Invalid number of layers {n} in {spec}, it should be from {} to {}",
+                LAYER_RANGE.start(),
+                LAYER_RANGE.end()
+            )),
+        };
+        match spec.split_once(':') {
+            Some(("cube", n)) => Ok(SessionType::Cube(CubePuzzle::Nnn(layers(n)?))),
+            Some(("fto", n)) => Ok(SessionType::Octa(OctaPuzzle::Fto(layers(n)?))),
+            None if spec == "pentultimate" => Ok(SessionType::Dodeca(DodecaPuzzle::Pentultimate)),
+            None if spec == "megaminx" => Ok(SessionType::Dodeca(DodecaPuzzle::Megaminx)),
+            None if spec == "little-chop" => Ok(SessionType::RDodeca(RDodecaPuzzle::LittleChop)),
+            _ => Err(eyre!("Unknown puzzle {spec}")),
+        }
+    }
+}
+
+impl SessionType {
+    /// The family of puzzles sharing a ray system, used for keybinds.
+    pub fn family(&self) -> &'static str {
+        match self {
+            SessionType::Cube(_) => "cube",
+            SessionType::Octa(_) => "fto",
+            SessionType::Dodeca(_) => "dodeca",
+            SessionType::RDodeca(_) => "rhombic-dodeca",
+        }
+    }
+}
+
 pub enum SessionEnum {
     Cube(CubePuzzle, Session<CubeRay>),
     Octa(OctaPuzzle, Session<OctaRay>),
@@ -245,18 +576,12 @@ pub enum SessionEnum {
 }
 
 impl SessionType {
-    pub fn make_session_enum(
-        self,
-        window_size: (u32, u32),
-        context: &three_d::Context,
-        prefs: &Preferences,
-    ) -> SessionEnum {
-        match self {
+    pub fn make_session_enum(self, window_size: (u32, u32), prefs: &Preferences) -> SessionEnum {
+        let mut session = match self {
             SessionType::Cube(ps @ CubePuzzle::Nnn(n)) => SessionEnum::Cube(
                 ps,
                 Session::from_concrete(make_concrete_puzzle(
                     window_size,
-                    context,
                     render::cube::nnn_seeds(n, &prefs.concrete),
                     prefs,
                 )),
@@ -265,7 +590,6 @@ impl SessionType {
                 ps,
                 Session::from_concrete(make_concrete_puzzle(
                     window_size,
-                    context,
                     render::octa::fto_seeds(n, &prefs.concrete),
                     prefs,
                 )),
@@ -274,7 +598,6 @@ impl SessionType {
                 ps,
                 Session::from_concrete(make_concrete_puzzle(
                     window_size,
-                    context,
                     render::dodeca::pentultimate_seeds(&prefs.concrete),
                     prefs,
                 )),
@@ -283,7 +606,6 @@ impl SessionType {
                 ps,
                 Session::from_concrete(make_concrete_puzzle(
                     window_size,
-                    context,
                     render::dodeca::mega_seeds(&prefs.concrete),
                     prefs,
                 )),
@@ -292,21 +614,41 @@ impl SessionType {
                 ps,
                 Session::from_concrete(make_concrete_puzzle(
                     window_size,
-                    context,
                     render::r_dodeca::little_chop_seeds(&prefs.concrete),
                     prefs,
                 )),
             ),
+        };
+        if let Some(camera) = prefs.cameras.get(&self.to_string()) {
+            session.set_camera_facings(camera);
         }
+        session
     }
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct SessionLog {
     pub version: String,
+    /// The layout of the log, see `log_format::LOG_SCHEMA`.
+    /// Logs from before schemas leave it out and have the layout of schema 1.
+    #[serde(default)]
+    pub schema: u32,
     pub session_type: SessionType,
-    pub scramble: Vec<Vec<String>>,
-    pub twists: Vec<((String, i8), Vec<Vec<i8>>)>,
+    /// The orientation of each piece, by ray name.
+    pub scramble: Vec<NamedOrientation>,
+    /// The solved positions of the pieces in `scramble`, for sparse puzzles.
+    /// Missing if `scramble` has every piece in order.
+    #[serde(default)]
+    pub scramble_positions: Option<Vec<usize>>,
+    pub twists: Vec<((String, i8), Vec<Grip>)>,
+    /// Only written for reference. It is recomputed from the twists when loading.
+    #[serde(default)]
+    pub move_count: Option<MoveCount>,
+    #[serde(default)]
+    pub blindfold: Option<BlindfoldResult>,
+    /// The camera facings, if the preferences say to save them.
+    #[serde(default)]
+    pub camera: Option<Vec<CameraFacing>>,
 }
 
 impl SessionEnum {
@@ -337,6 +679,39 @@ impl SessionEnum {
         };
     }
 
+    /// The format of the file the session was last saved to or loaded from, if any.
+    pub fn save_format(&self) -> Option<LogFormat> {
+        let format = match self {
+            SessionEnum::Cube(_, ref session) => session.save_format,
+            SessionEnum::Octa(_, ref session) => session.save_format,
+            SessionEnum::Dodeca(_, ref session) => session.save_format,
+            SessionEnum::RDodeca(_, ref session) => session.save_format,
+        };
+        self.save_path().as_ref().map(|_| format)
+    }
+
+    pub fn set_save_format(&mut self, val: LogFormat) {
+        match self {
+            SessionEnum::Cube(_, ref mut session) => session.save_format = val,
+            SessionEnum::Octa(_, ref mut session) => session.save_format = val,
+            SessionEnum::Dodeca(_, ref mut session) => session.save_format = val,
+            SessionEnum::RDodeca(_, ref mut session) => session.save_format = val,
+        };
+    }
+
+    /// Scrambles the same way every time for the same seed, in the same version of laminated.
+    pub fn scramble_seeded(&mut self, seed: u64) {
+        use rand::SeedableRng;
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        match self {
+            SessionEnum::Cube(_, ref mut session) => session.scramble_with(&mut rng),
+            SessionEnum::Octa(_, ref mut session) => session.scramble_with(&mut rng),
+            SessionEnum::Dodeca(_, ref mut session) => session.scramble_with(&mut rng),
+            SessionEnum::RDodeca(_, ref mut session) => session.scramble_with(&mut rng),
+        }
+    }
+
     pub fn version(&self) -> &String {
         match self {
             SessionEnum::Cube(_, ref session) => &session.version,
@@ -346,45 +721,86 @@ impl SessionEnum {
         }
     }
 
-    pub fn to_log(&self) -> SessionLog {
-        let (scramble, twists) = match self {
-            Self::Cube(_, session) => session.extract_log(),
-            Self::Octa(_, session) => session.extract_log(),
-            Self::Dodeca(_, session) => session.extract_log(),
-            Self::RDodeca(_, session) => session.extract_log(),
+    pub fn to_log(&self, include_camera: bool) -> SessionLog {
+        let ((scramble, scramble_positions, twists), move_count, blindfold, camera) = match self {
+            Self::Cube(_, s) => (
+                s.extract_log(),
+                s.move_count(),
+                s.blindfold_result,
+                s.camera_facing_list(),
+            ),
+            Self::Octa(_, s) => (
+                s.extract_log(),
+                s.move_count(),
+                s.blindfold_result,
+                s.camera_facing_list(),
+            ),
+            Self::Dodeca(_, s) => (
+                s.extract_log(),
+                s.move_count(),
+                s.blindfold_result,
+                s.camera_facing_list(),
+            ),
+            Self::RDodeca(_, s) => (
+                s.extract_log(),
+                s.move_count(),
+                s.blindfold_result,
+                s.camera_facing_list(),
+            ),
         };
 
         SessionLog {
             version: VERSION.to_string(),
+            schema: log_format::LOG_SCHEMA,
             session_type: self.get_type(),
             scramble,
+            scramble_positions,
             twists,
+            move_count: Some(move_count),
+            blindfold,
+            camera: include_camera.then_some(camera),
+        }
+    }
+
+    pub fn set_camera_facings(&mut self, facings: &[CameraFacing]) {
+        match self {
+            Self::Cube(_, s) => s.set_camera_facings(facings),
+            Self::Octa(_, s) => s.set_camera_facings(facings),
+            Self::Dodeca(_, s) => s.set_camera_facings(facings),
+            Self::RDodeca(_, s) => s.set_camera_facings(facings),
         }
     }
 
-    pub fn save_as(&mut self, path: &std::path::PathBuf) -> eyre::Result<std::path::PathBuf> {
-        std::fs::write(path, serde_json::to_string(&self.to_log())?)?;
+    pub fn save_as(
+        &mut self,
+        path: &std::path::PathBuf,
+        include_camera: bool,
+        format: LogFormat,
+    ) -> eyre::Result<std::path::PathBuf> {
+        std::fs::write(
+            path,
+            log_format::encode(&self.to_log(include_camera), format)?,
+        )?;
         self.set_save_path(Some(path.clone()));
+        self.set_save_format(format);
         Ok(path.clone())
     }
 
-    fn from_log(
+    /// Makes the session of the log. `path` is where it is saved, if anywhere.
+    pub fn from_log(
         log: SessionLog,
         window_size: (u32, u32),
-        context: &three_d::Context,
-        path: std::path::PathBuf,
+        path: Option<std::path::PathBuf>,
         prefs: &Preferences,
     ) -> eyre::Result<Self> {
-        let mut session = log
-            .session_type
-            .make_session_enum(window_size, context, prefs);
+        let mut session = log.session_type.make_session_enum(window_size, prefs);
         match &mut session {
             SessionEnum::Cube(_, ref mut session) => session.process_log(log),
             SessionEnum::Octa(_, ref mut session) => session.process_log(log),
             SessionEnum::Dodeca(_, ref mut session) => session.process_log(log),
             SessionEnum::RDodeca(_, ref mut session) => session.process_log(log),
         }?;
-        session.set_save_path(Some(path));
+        session.set_save_path(path);
 
         Ok(session)
     }
@@ -392,13 +808,23 @@ impl SessionEnum {
     pub fn load(
         path: std::path::PathBuf,
         window_size: (u32, u32),
-        context: &three_d::Context,
         prefs: &Preferences,
     ) -> eyre::Result<Self> {
-        let file = std::fs::File::open(path.clone())?;
-        let reader = std::io::BufReader::new(file);
-        let session_log: SessionLog = serde_json::from_reader(reader)?;
-        Self::from_log(session_log, window_size, context, path, prefs)
+        let bytes = std::fs::read(&path)?;
+        Self::from_bytes(&bytes, path, window_size, prefs)
+    }
+
+    /// Makes the session of a log file that was already read from `path`.
+    pub fn from_bytes(
+        bytes: &[u8],
+        path: std::path::PathBuf,
+        window_size: (u32, u32),
+        prefs: &Preferences,
+    ) -> eyre::Result<Self> {
+        let session_log = log_format::decode(bytes)?;
+        let mut session = Self::from_log(session_log, window_size, Some(path), prefs)?;
+        session.set_save_format(log_format::detect(bytes));
+        Ok(session)
     }
 
     pub fn replace_concrete_puzzle_from(&mut self, other: SessionEnum) {
@@ -423,3 +849,128 @@ impl SessionEnum {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW_SIZE: (u32, u32) = (800, 600);
+
+    fn is_solved(session: &SessionEnum) -> bool {
+        match session {
+            SessionEnum::Cube(_, s) => s.concrete_puzzle.puzzle.is_solved(),
+            SessionEnum::Octa(_, s) => s.concrete_puzzle.puzzle.is_solved(),
+            SessionEnum::Dodeca(_, s) => s.concrete_puzzle.puzzle.is_solved(),
+            SessionEnum::RDodeca(_, s) => s.concrete_puzzle.puzzle.is_solved(),
+        }
+    }
+
+    /// Loads the log like opening it does, without drawing anything.
+    /// Returns whether the puzzle is solved after the scramble and after the twists.
+    fn replay_fixture(bytes: &[u8], prefs: &Preferences) -> (bool, bool) {
+        let path = std::path::PathBuf::from("fixture.log");
+        let mut scramble_only = log_format::decode(bytes).unwrap();
+        assert_eq!(scramble_only.schema, log_format::LOG_SCHEMA);
+        scramble_only.twists.clear();
+        let scrambled = SessionEnum::from_log(scramble_only, WINDOW_SIZE, None, prefs).unwrap();
+
+        let session = SessionEnum::from_bytes(bytes, path.clone(), WINDOW_SIZE, prefs).unwrap();
+        assert_eq!(session.save_path(), &Some(path));
+        assert_eq!(session.save_format(), Some(log_format::detect(bytes)));
+        (is_solved(&scrambled), is_solved(&session))
+    }
+
+    #[test]
+    fn specs() {
+        for spec in ["cube:5", "fto:4", "pentultimate", "megaminx", "little-chop"] {
+            assert_eq!(spec.parse::<SessionType>().unwrap().to_string(), spec);
+        }
+        for spec in [
+            "cube",
+            "cube:-2",
+            "cube:1",
+            "fto:1",
+            "fto:64",
+            "fto:x",
+            "megaminx:3",
+            "tetra",
+        ] {
+            assert!(spec.parse::<SessionType>().is_err(), "{spec}");
+        }
+    }
+
+    /// Each fixture is a scramble and its solution, saved by an earlier version.
+    /// If they stop loading or stop ending solved, old logs are being read wrong.
+    #[test]
+    fn fixtures_replay() {
+        let fixtures: [(&str, &[u8]); 6] = [
+            ("cube-3", include_bytes!("../fixtures/logs/cube-3.log")),
+            ("fto-3", include_bytes!("../fixtures/logs/fto-3.log")),
+            (
+                "pentultimate",
+                include_bytes!("../fixtures/logs/pentultimate.log"),
+            ),
+            ("megaminx", include_bytes!("../fixtures/logs/megaminx.log")),
+            (
+                "little-chop",
+                include_bytes!("../fixtures/logs/little-chop.log"),
+            ),
+            (
+                "cube-4-compact",
+                include_bytes!("../fixtures/logs/cube-4-compact.log"),
+            ),
+        ];
+        for sparse in [false, true] {
+            let mut prefs = Preferences::default();
+            prefs.concrete.sparse = sparse;
+            for (name, bytes) in fixtures {
+                let replayed = replay_fixture(bytes, &prefs);
+                assert_eq!(replayed, (false, true), "fixture {name}, sparse {sparse}");
+            }
+        }
+    }
+
+    #[test]
+    fn sparse_logs() {
+        let mut sparse = Preferences::default();
+        sparse.concrete.sparse = true;
+        for spec in ["cube:3", "fto:3"] {
+            let session_type: SessionType = spec.parse().unwrap();
+            let mut session = session_type.make_session_enum(WINDOW_SIZE, &sparse);
+            session.scramble_seeded(1);
+            let log = session.to_log(false);
+            assert!(log.scramble_positions.is_some(), "{spec}");
+            let json = serde_json::to_string(&log).unwrap();
+
+            let loaded = SessionEnum::from_log(log, WINDOW_SIZE, None, &sparse).unwrap();
+            let reloaded = serde_json::to_string(&loaded.to_log(false)).unwrap();
+            assert_eq!(reloaded, json, "{spec}");
+            assert!(!is_solved(&loaded), "{spec}");
+
+            // a full puzzle solves the pieces the sparse one left out
+            let log = serde_json::from_str(&json).unwrap();
+            let full = SessionEnum::from_log(log, WINDOW_SIZE, None, &Preferences::default());
+            assert!(!is_solved(&full.unwrap()), "{spec}");
+        }
+    }
+
+    #[test]
+    fn log_errors() {
+        let log = |version: &str| {
+            let json = format!(
+                r#"{{"version":"{version}","session_type":{{"Cube":{{"Nnn":3}}}},
+                "scramble":[],"twists":[[["X",1],[[1,0]]]]}}"#
+            );
+            log_format::decode(json.as_bytes()).unwrap()
+        };
+        let prefs = Preferences::default();
+
+        let err = SessionEnum::from_log(log(VERSION), WINDOW_SIZE, None, &prefs).err();
+        assert_eq!(err.unwrap().to_string(), "Invalid ray name");
+        let err = SessionEnum::from_log(log("0.3.0"), WINDOW_SIZE, None, &prefs).err();
+        assert_eq!(
+            err.unwrap().to_string(),
+            "Invalid ray name (loading from version 0.3.0)"
+        );
+    }
+}
@@ -56,6 +56,77 @@ pub mod enum_map_serde {
     }
 }
 
+pub mod key_names {
+    use three_d::Key;
+
+    pub const KEYS: &[(Key, &str)] = &[
+        (Key::ArrowDown, "Down"),
+        (Key::ArrowLeft, "Left"),
+        (Key::ArrowRight, "Right"),
+        (Key::ArrowUp, "Up"),
+        (Key::Escape, "Escape"),
+        (Key::Tab, "Tab"),
+        (Key::Backspace, "Backspace"),
+        (Key::Enter, "Enter"),
+        (Key::Space, "Space"),
+        (Key::Insert, "Insert"),
+        (Key::Delete, "Delete"),
+        (Key::Home, "Home"),
+        (Key::End, "End"),
+        (Key::PageUp, "PageUp"),
+        (Key::PageDown, "PageDown"),
+        (Key::Num0, "0"),
+        (Key::Num1, "1"),
+        (Key::Num2, "2"),
+        (Key::Num3, "3"),
+        (Key::Num4, "4"),
+        (Key::Num5, "5"),
+        (Key::Num6, "6"),
+        (Key::Num7, "7"),
+        (Key::Num8, "8"),
+        (Key::Num9, "9"),
+        (Key::A, "A"),
+        (Key::B, "B"),
+        (Key::C, "C"),
+        (Key::D, "D"),
+        (Key::E, "E"),
+        (Key::F, "F"),
+        (Key::G, "G"),
+        (Key::H, "H"),
+        (Key::I, "I"),
+        (Key::J, "J"),
+        (Key::K, "K"),
+        (Key::L, "L"),
+        (Key::M, "M"),
+        (Key::N, "N"),
+        (Key::O, "O"),
+        (Key::P, "P"),
+        (Key::Q, "Q"),
+        (Key::R, "R"),
+        (Key::S, "S"),
+        (Key::T, "T"),
+        (Key::U, "U"),
+        (Key::V, "V"),
+        (Key::W, "W"),
+        (Key::X, "X"),
+        (Key::Y, "Y"),
+        (Key::Z, "Z"),
+    ];
+
+    pub fn key_name(key: Key) -> &'static str {
+        KEYS.iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, name)| *name)
+            .expect("all keys have names")
+    }
+
+    pub fn key_from_name(name: &str) -> Option<Key> {
+        KEYS.iter()
+            .find(|(_, n)| n.eq_ignore_ascii_case(name))
+            .map(|(k, _)| *k)
+    }
+}
+
 pub mod color {
     use serde::Deserialize;
     use serde::Serialize;
@@ -81,6 +152,16 @@ pub mod color {
             Srgba::new_opaque(self.r, self.g, self.b)
         }
 
+        /// Blends towards other, with t==0.0 => self and t==1.0 => other.
+        pub fn mix(&self, other: Color, t: f32) -> Color {
+            let blend = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+            Color {
+                r: blend(self.r, other.r),
+                g: blend(self.g, other.g),
+                b: blend(self.b, other.b),
+            }
+        }
+
         pub fn as_array(&self) -> [u8; 3] {
             [self.r, self.g, self.b]
         }
@@ -0,0 +1,107 @@
+//! The window and its event loop. This does what `three_d::Window` does,
+//! and also passes on the files dropped on the window, which `three_d::Window` leaves out.
+use std::path::PathBuf;
+use three_d::{
+    Context, FrameInput, FrameInputGenerator, FrameOutput, SurfaceSettings, WindowedContext,
+};
+use winit::dpi::LogicalSize;
+use winit::event::{Event, WindowEvent};
+use winit::event_loop::{ControlFlow, EventLoop};
+use winit::window::WindowBuilder;
+
+pub struct Window {
+    window: winit::window::Window,
+    event_loop: EventLoop<()>,
+    gl: WindowedContext,
+}
+
+impl Window {
+    /// Makes a window of the size, which it cannot grow past.
+    pub fn new(title: &str, size: (u32, u32)) -> eyre::Result<Self> {
+        let event_loop = EventLoop::new();
+        let size = LogicalSize::new(size.0 as f64, size.1 as f64);
+        let window = WindowBuilder::new()
+            .with_title(title)
+            .with_min_inner_size(LogicalSize::new(2, 2))
+            .with_inner_size(size)
+            .with_max_inner_size(size)
+            .build(&event_loop)?;
+        let mut surface_settings = SurfaceSettings::default();
+        let gl = WindowedContext::from_winit_window(&window, surface_settings).or_else(|_| {
+            // not every graphics card has multisampling
+            surface_settings.multisamples = 0;
+            WindowedContext::from_winit_window(&window, surface_settings)
+        })?;
+        Ok(Self {
+            window,
+            event_loop,
+            gl,
+        })
+    }
+
+    /// The logical size of the window.
+    pub fn size(&self) -> (u32, u32) {
+        self.window
+            .inner_size()
+            .to_logical::<f64>(self.window.scale_factor())
+            .into()
+    }
+
+    pub fn gl(&self) -> Context {
+        (*self.gl).clone()
+    }
+
+    /// Calls `callback` each frame with the input and the files dropped since the last frame,
+    /// until it asks to exit or the window is closed.
+    /// When the window is closed, `callback` is called once more with `closing` set,
+    /// so it can save what it has to.
+    pub fn render_loop(
+        self,
+        mut callback: impl FnMut(FrameInput, Vec<PathBuf>, bool) -> FrameOutput + 'static,
+    ) -> ! {
+        let Self {
+            window,
+            event_loop,
+            gl,
+        } = self;
+        let mut frame_input_generator = FrameInputGenerator::from_winit_window(&window);
+        let mut dropped_files = vec![];
+        event_loop.run(move |event, _, control_flow| match event {
+            Event::MainEventsCleared => window.request_redraw(),
+            Event::RedrawRequested(_) => {
+                let frame_input = frame_input_generator.generate(&gl);
+                let frame_output = callback(frame_input, std::mem::take(&mut dropped_files), false);
+                if frame_output.exit {
+                    *control_flow = ControlFlow::Exit;
+                    return;
+                }
+                if frame_output.swap_buffers {
+                    gl.swap_buffers().expect("should swap buffers");
+                }
+                if frame_output.wait_next_event {
+                    *control_flow = ControlFlow::Wait;
+                } else {
+                    *control_flow = ControlFlow::Poll;
+                    window.request_redraw();
+                }
+            }
+            Event::WindowEvent { ref event, .. } => {
+                frame_input_generator.handle_winit_window_event(event);
+                match event {
+                    WindowEvent::Resized(physical_size) => gl.resize(*physical_size),
+                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                        gl.resize(**new_inner_size)
+                    }
+                    WindowEvent::DroppedFile(path) => dropped_files.push(path.clone()),
+                    WindowEvent::CloseRequested => {
+                        let frame_input = frame_input_generator.generate(&gl);
+                        callback(frame_input, std::mem::take(&mut dropped_files), true);
+                        *control_flow = ControlFlow::Exit;
+                    }
+                    _ => (),
+                }
+            }
+            _ => (),
+        })
+    }
+}